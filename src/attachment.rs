@@ -0,0 +1,177 @@
+use crate::ical_line_parser::{split_unquoted, unquote};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum AttachmentParseError {
+    #[error("Invalid base64 character {c:?} at position {position}")]
+    InvalidBase64Character { c: char, position: usize },
+    #[error("Unsupported attachment encoding {encoding:?}")]
+    UnsupportedEncoding { encoding: String },
+}
+
+/// An `ATTACH` property, either referencing a document by URI or carrying it inline.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Attachment {
+    Uri {
+        uri: String,
+        fmt_type: Option<String>,
+    },
+    Binary {
+        data: Vec<u8>,
+        fmt_type: Option<String>,
+    },
+}
+
+impl Attachment {
+    pub fn fmt_type(&self) -> Option<&str> {
+        match self {
+            Attachment::Uri { fmt_type, .. } => fmt_type.as_deref(),
+            Attachment::Binary { fmt_type, .. } => fmt_type.as_deref(),
+        }
+    }
+
+    /// Builds an attachment from the `;`-separated parameters (if any) and the value of an
+    /// `ATTACH` line.
+    pub(crate) fn parse(params: Option<&str>, value: &str) -> Result<Self, AttachmentParseError> {
+        let mut fmt_type = None;
+        let mut encoding = None;
+
+        for param in params
+            .into_iter()
+            .flat_map(|params| split_unquoted(params, ';'))
+        {
+            if let Some((key, param_value)) = param.split_once('=') {
+                let param_value = unquote(param_value);
+                match key.to_ascii_uppercase().as_str() {
+                    "FMTTYPE" => fmt_type = Some(param_value.to_owned()),
+                    "ENCODING" => encoding = Some(param_value),
+                    _ => {} // VALUE=BINARY is implied by ENCODING
+                }
+            }
+        }
+
+        match encoding {
+            None => Ok(Attachment::Uri {
+                uri: value.to_owned(),
+                fmt_type,
+            }),
+            Some(encoding) if encoding.eq_ignore_ascii_case("BASE64") => Ok(Attachment::Binary {
+                data: decode_base64(value)?,
+                fmt_type,
+            }),
+            Some(encoding) => Err(AttachmentParseError::UnsupportedEncoding {
+                encoding: encoding.to_owned(),
+            }),
+        }
+    }
+
+    /// The full `ATTACH` property line.
+    pub(crate) fn to_ical_line(&self) -> String {
+        let fmt_type = self
+            .fmt_type()
+            .map(|fmt_type| format!(";FMTTYPE={fmt_type}"))
+            .unwrap_or_default();
+
+        match self {
+            Attachment::Uri { uri, .. } => format!("ATTACH{fmt_type}:{uri}"),
+            Attachment::Binary { data, .. } => format!(
+                "ATTACH{fmt_type};ENCODING=BASE64;VALUE=BINARY:{}",
+                encode_base64(data)
+            ),
+        }
+    }
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn encode_base64(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+
+    for chunk in data.chunks(3) {
+        let buffer = chunk.iter().enumerate().fold(0u32, |buffer, (i, byte)| {
+            buffer | (*byte as u32) << (16 - 8 * i)
+        });
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64_ALPHABET[(buffer >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+fn decode_base64(s: &str) -> Result<Vec<u8>, AttachmentParseError> {
+    let mut data = Vec::with_capacity(s.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for (position, c) in s.trim_end_matches('=').char_indices() {
+        let value = match c {
+            'A'..='Z' => c as u32 - 'A' as u32,
+            'a'..='z' => c as u32 - 'a' as u32 + 26,
+            '0'..='9' => c as u32 - '0' as u32 + 52,
+            '+' => 62,
+            '/' => 63,
+            _ => return Err(AttachmentParseError::InvalidBase64Character { c, position }),
+        };
+
+        buffer = (buffer << 6) | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            data.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64() {
+        assert_eq!(decode_base64("").unwrap(), b"");
+        assert_eq!(decode_base64("Zg==").unwrap(), b"f");
+        assert_eq!(decode_base64("Zm8=").unwrap(), b"fo");
+        assert_eq!(decode_base64("Zm9v").unwrap(), b"foo");
+        assert_eq!(decode_base64("Zm9vYmFy").unwrap(), b"foobar");
+        assert_eq!(decode_base64("/+8=").unwrap(), [0xff, 0xef]);
+        assert!(decode_base64("Zm9v!").is_err());
+
+        for data in [&b""[..], b"f", b"fo", b"foo", b"foobar", &[0xff, 0xef]] {
+            assert_eq!(decode_base64(&encode_base64(data)).unwrap(), data);
+        }
+        assert_eq!(encode_base64(b"fo"), "Zm8=");
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            Attachment::parse(None, "https://example.com/agenda.pdf").unwrap(),
+            Attachment::Uri {
+                uri: "https://example.com/agenda.pdf".to_owned(),
+                fmt_type: None
+            }
+        );
+        assert_eq!(
+            Attachment::parse(
+                Some("FMTTYPE=text/plain;ENCODING=BASE64;VALUE=BINARY"),
+                "aGVsbG8="
+            )
+            .unwrap(),
+            Attachment::Binary {
+                data: b"hello".to_vec(),
+                fmt_type: Some("text/plain".to_owned())
+            }
+        );
+        assert!(Attachment::parse(Some("ENCODING=8BIT"), "hello").is_err());
+    }
+}
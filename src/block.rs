@@ -1,11 +1,20 @@
+use crate::ical_line_parser::strip_prefix_ignore_case;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum BlockParseError {
     #[error("Block must start with BEGIN:")]
     BlockNotStartingWithBEGIN,
+    #[error("Blocks nested more than {MAX_NESTING} levels deep")]
+    TooDeeplyNested,
 }
 
+/// How deep blocks can be nested (a VALARM in a VEVENT in a VCALENDAR is 3 levels), to
+/// bound the recursion on untrusted input.
+const MAX_NESTING: usize = 32;
+
+/// A `BEGIN:`/`END:` delimited component as unfolded lines, before any interpretation. A
+/// [`crate::VEvent`] converts back to one with `Block::from(&event)`.
 #[derive(Debug, Clone, Default)]
 pub struct Block {
     pub name: String,
@@ -19,10 +28,57 @@ impl Block {
     }
 }
 
+/// Maximum length of a content line, in octets, before it's folded (RFC 5545, 3.1).
+const MAX_LINE_LENGTH: usize = 75;
+
+/// Writes `line` followed by CRLF, folded so that no line is longer than
+/// [`MAX_LINE_LENGTH`] octets, splitting only between characters.
+fn write_folded(f: &mut std::fmt::Formatter<'_>, line: &str) -> std::fmt::Result {
+    let mut rest = line;
+    // continuation lines start with a space, which counts towards their length
+    let mut max_length = MAX_LINE_LENGTH;
+    while rest.len() > max_length {
+        let mut idx = max_length;
+        while !rest.is_char_boundary(idx) {
+            idx -= 1;
+        }
+        write!(f, "{}\r\n ", &rest[..idx])?;
+        rest = &rest[idx..];
+        max_length = MAX_LINE_LENGTH - 1;
+    }
+    write!(f, "{rest}\r\n")
+}
+
+/// The block as iCalendar text: `BEGIN:`, the lines, the nested blocks and `END:`, each
+/// line folded and terminated by CRLF.
+impl std::fmt::Display for Block {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_folded(f, &format!("BEGIN:{}", self.name))?;
+        for line in &self.inner_lines {
+            write_folded(f, line)?;
+        }
+        for block in &self.inner_blocks {
+            write!(f, "{block}")?;
+        }
+        write_folded(f, &format!("END:{}", self.name))
+    }
+}
+
 impl<'a> TryFrom<&'a [String]> for Block {
     type Error = BlockParseError;
 
     fn try_from(lines: &'a [String]) -> Result<Self, Self::Error> {
+        Block::parse(lines, 1)
+    }
+}
+
+impl Block {
+    /// Parses the block starting at the first of `lines`, `nesting` levels deep.
+    fn parse(lines: &[String], nesting: usize) -> Result<Self, BlockParseError> {
+        if nesting > MAX_NESTING {
+            return Err(BlockParseError::TooDeeplyNested);
+        }
+
         log::trace!(
             "process_lines_skipping_inner, lines.len() == {}",
             lines.len()
@@ -31,7 +87,10 @@ impl<'a> TryFrom<&'a [String]> for Block {
         let mut depth = 1;
         let mut position = 0;
 
-        if let Some(name) = lines[position].strip_prefix("BEGIN:") {
+        if let Some(name) = lines
+            .first()
+            .and_then(|line| strip_prefix_ignore_case(line, "BEGIN:"))
+        {
             let mut inner_block_start = None;
 
             position += 1;
@@ -42,24 +101,22 @@ impl<'a> TryFrom<&'a [String]> for Block {
                 let line = &lines[position];
                 position += 1;
 
-                if line.starts_with("BEGIN:") {
+                if strip_prefix_ignore_case(line, "BEGIN:").is_some() {
                     if inner_block_start.is_none() {
                         // only save the first one!
                         inner_block_start = Some(position - 1);
                     }
                     depth += 1;
-                } else if line.starts_with("END:") {
+                } else if strip_prefix_ignore_case(line, "END:").is_some() {
                     depth -= 1;
 
                     if depth == 1 {
-                        // process inner!
-                        log::trace!(
-                            "About to go in {}..{}",
-                            inner_block_start.unwrap(),
-                            position
-                        );
-                        inner_blocks.push(lines[inner_block_start.unwrap()..position].try_into()?);
-                        inner_block_start = None;
+                        // process inner! Getting back to depth 1 takes a BEGIN first, which
+                        // sets the start.
+                        if let Some(start) = inner_block_start.take() {
+                            log::trace!("About to go in {}..{}", start, position);
+                            inner_blocks.push(Block::parse(&lines[start..position], nesting + 1)?);
+                        }
                     }
                 } else if depth == 1 {
                     inner_lines.push(line.to_owned());
@@ -76,3 +133,236 @@ impl<'a> TryFrom<&'a [String]> for Block {
         }
     }
 }
+
+impl Block {
+    /// Builds the block from unfolded `lines` while they are read, keeping the nested blocks
+    /// still open on a stack instead of collecting the lines first. Lines after the `END:` of
+    /// the outer block are not read.
+    pub(crate) fn from_lines<E: From<BlockParseError>>(
+        lines: impl IntoIterator<Item = Result<String, E>>,
+    ) -> Result<Self, E> {
+        let mut lines = lines.into_iter();
+        let first_line = lines.next().transpose()?.unwrap_or_default();
+        let Some(name) = strip_prefix_ignore_case(&first_line, "BEGIN:") else {
+            return Err(BlockParseError::BlockNotStartingWithBEGIN.into());
+        };
+        let mut outer = Block {
+            name: name.to_owned(),
+            ..Default::default()
+        };
+        let mut open_blocks: Vec<Block> = Vec::new();
+
+        for line in lines {
+            let line = line?;
+            if let Some(name) = strip_prefix_ignore_case(&line, "BEGIN:") {
+                if open_blocks.len() + 1 >= MAX_NESTING {
+                    return Err(BlockParseError::TooDeeplyNested.into());
+                }
+                open_blocks.push(Block {
+                    name: name.to_owned(),
+                    ..Default::default()
+                });
+            } else if strip_prefix_ignore_case(&line, "END:").is_some() {
+                let Some(block) = open_blocks.pop() else {
+                    return Ok(outer);
+                };
+                open_blocks
+                    .last_mut()
+                    .unwrap_or(&mut outer)
+                    .inner_blocks
+                    .push(block);
+            } else {
+                open_blocks
+                    .last_mut()
+                    .unwrap_or(&mut outer)
+                    .inner_lines
+                    .push(line);
+            }
+        }
+
+        // as when parsing a slice, unterminated nested blocks are dropped
+        Ok(outer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ical_line_parser::ICalLineParser, vevent::tests::parse_event};
+
+    fn block(lines: &[&str]) -> Result<Block, BlockParseError> {
+        Block::try_from(
+            lines
+                .iter()
+                .map(|line| line.to_string())
+                .collect::<Vec<_>>()
+                .as_slice(),
+        )
+    }
+
+    #[test]
+    fn flat() {
+        let block = block(&["BEGIN:VEVENT", "UID:1", "SUMMARY:Flat", "END:VEVENT"]).unwrap();
+
+        assert_eq!(block.name(), "VEVENT");
+        assert_eq!(block.inner_lines, ["UID:1", "SUMMARY:Flat"]);
+        assert!(block.inner_blocks.is_empty());
+    }
+
+    #[test]
+    fn sibling_blocks() {
+        let block = block(&[
+            "BEGIN:VCALENDAR",
+            "VERSION:2.0",
+            "BEGIN:VEVENT",
+            "UID:1",
+            "END:VEVENT",
+            "PRODID:-//Example//EN",
+            "BEGIN:VTODO",
+            "UID:2",
+            "END:VTODO",
+            "END:VCALENDAR",
+        ])
+        .unwrap();
+
+        // lines after a nested block still belong to the outer one
+        assert_eq!(block.inner_lines, ["VERSION:2.0", "PRODID:-//Example//EN"]);
+        assert_eq!(
+            block
+                .inner_blocks
+                .iter()
+                .map(|block| (block.name(), block.inner_lines.clone()))
+                .collect::<Vec<_>>(),
+            [
+                ("VEVENT", vec!["UID:1".to_owned()]),
+                ("VTODO", vec!["UID:2".to_owned()])
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_blocks() {
+        let block = block(&[
+            "BEGIN:VCALENDAR",
+            "BEGIN:VEVENT",
+            "UID:1",
+            "BEGIN:VALARM",
+            "ACTION:DISPLAY",
+            "TRIGGER:-PT15M",
+            "END:VALARM",
+            "SUMMARY:With alarm",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ])
+        .unwrap();
+
+        assert!(block.inner_lines.is_empty());
+        let event = &block.inner_blocks[0];
+        assert_eq!(event.name(), "VEVENT");
+        assert_eq!(event.inner_lines, ["UID:1", "SUMMARY:With alarm"]);
+        let alarm = &event.inner_blocks[0];
+        assert_eq!(alarm.name(), "VALARM");
+        assert_eq!(alarm.inner_lines, ["ACTION:DISPLAY", "TRIGGER:-PT15M"]);
+        assert!(alarm.inner_blocks.is_empty());
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(matches!(
+            block(&["UID:1", "END:VEVENT"]),
+            Err(BlockParseError::BlockNotStartingWithBEGIN)
+        ));
+
+        // a missing END of the outer block is tolerated, while an unterminated nested block
+        // is dropped since it's impossible to tell where it ends
+        let block = block(&["BEGIN:VCALENDAR", "VERSION:2.0", "BEGIN:VEVENT", "UID:1"]).unwrap();
+        assert_eq!(block.inner_lines, ["VERSION:2.0"]);
+        assert!(block.inner_blocks.is_empty());
+    }
+
+    #[test]
+    fn from_lines() {
+        let from_lines = |lines: &[&str]| {
+            Block::from_lines(
+                lines
+                    .iter()
+                    .map(|line| Ok::<_, BlockParseError>(line.to_string())),
+            )
+        };
+        let lines = [
+            "BEGIN:VCALENDAR",
+            "VERSION:2.0",
+            "BEGIN:VEVENT",
+            "UID:1",
+            "BEGIN:VALARM",
+            "ACTION:DISPLAY",
+            "END:VALARM",
+            "END:VEVENT",
+            "PRODID:-//Example//EN",
+            "BEGIN:VTODO",
+            "UID:2",
+            "END:VTODO",
+            "END:VCALENDAR",
+        ];
+
+        assert_eq!(
+            from_lines(&lines).unwrap().to_string(),
+            block(&lines).unwrap().to_string()
+        );
+        assert_eq!(
+            from_lines(&lines[..4]).unwrap().to_string(),
+            block(&lines[..4]).unwrap().to_string()
+        );
+        // nothing is read after the end of the outer block
+        let mut with_error = lines
+            .iter()
+            .map(|line| Ok(line.to_string()))
+            .collect::<Vec<_>>();
+        with_error.push(Err(BlockParseError::TooDeeplyNested));
+        assert!(Block::from_lines(with_error).is_ok());
+
+        assert!(matches!(
+            from_lines(&["UID:1", "END:VEVENT"]),
+            Err(BlockParseError::BlockNotStartingWithBEGIN)
+        ));
+        assert!(matches!(
+            from_lines(&[]),
+            Err(BlockParseError::BlockNotStartingWithBEGIN)
+        ));
+        let deep = vec!["BEGIN:X"; MAX_NESTING + 1];
+        assert!(matches!(
+            from_lines(&deep),
+            Err(BlockParseError::TooDeeplyNested)
+        ));
+        assert!(from_lines(&deep[..MAX_NESTING]).is_ok());
+    }
+
+    #[test]
+    fn display() {
+        let event = parse_event(&[
+            "UID:display",
+            "DTSTART:20220106T100000Z",
+            "DESCRIPTION:A description long enough to be folded, with some non ASCII characters: àèìòù àèìòù",
+        ]);
+        let calendar = Block {
+            name: "VCALENDAR".to_owned(),
+            inner_lines: vec!["VERSION:2.0".to_owned()],
+            inner_blocks: vec![Block::from(&event)],
+        };
+
+        let text = calendar.to_string();
+        assert!(text.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VEVENT\r\n"));
+        assert!(text.ends_with("END:VEVENT\r\nEND:VCALENDAR\r\n"));
+        assert!(text.split("\r\n").all(|line| line.len() <= MAX_LINE_LENGTH));
+
+        // folding is undone by the parser
+        let lines = text.split("\r\n").collect::<Vec<_>>();
+        let unfolded = ICalLineParser::new(&lines).collect::<Vec<_>>();
+        let parsed = Block::try_from(unfolded.as_slice()).unwrap();
+        assert_eq!(parsed.inner_lines, calendar.inner_lines);
+        assert_eq!(
+            parsed.inner_blocks[0].inner_lines,
+            calendar.inner_blocks[0].inner_lines
+        );
+    }
+}
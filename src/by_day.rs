@@ -1,13 +1,17 @@
 use chrono::Weekday;
-use std::str::FromStr;
+use std::{fmt::Display, str::FromStr};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum ByDayParseError {
+    #[error("Empty BYDAY")]
+    Empty,
     #[error("Invalid weekday {w:?})")]
     InvalidWeekday { w: String },
     #[error("Invalid delta")]
     InvalidDelta(#[from] std::num::ParseIntError),
+    #[error("Delta {delta} out of range, must be between -53 and 53 and not 0")]
+    DeltaOutOfRange { delta: i32 },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -32,14 +36,11 @@ impl FromStr for ByDay {
     type Err = ByDayParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let tokens = s
-            .split(',')
-            .into_iter()
-            .filter(|s| !s.is_empty())
-            .collect::<Vec<_>>();
-
-        if tokens[0].len() > 2 {
-            Ok(ByDay::Delta(tokens[0].parse()?))
+        let tokens = s.split(',').filter(|s| !s.is_empty()).collect::<Vec<_>>();
+        let first = tokens.first().ok_or(ByDayParseError::Empty)?;
+
+        if first.len() > 2 {
+            Ok(ByDay::Delta(first.parse()?))
         } else {
             Ok(Self::Simple(
                 tokens
@@ -55,8 +56,17 @@ impl FromStr for Delta {
     type Err = ByDayParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let weekday = to_chrono_weekday(&s[s.len() - 2..])?;
-        let delta: i32 = s[..s.len() - 2].parse()?;
+        let idx_weekday = s
+            .len()
+            .checked_sub(2)
+            .filter(|idx| s.is_char_boundary(*idx))
+            .ok_or_else(|| ByDayParseError::InvalidWeekday { w: s.to_owned() })?;
+        let weekday = to_chrono_weekday(&s[idx_weekday..])?;
+        let delta: i32 = s[..idx_weekday].parse()?;
+        // RFC 5545 (3.3.10): ordwk = 1*2DIGIT ;1 to 53
+        if !(1..=53).contains(&delta.unsigned_abs()) {
+            return Err(ByDayParseError::DeltaOutOfRange { delta });
+        }
         Ok(Self { delta, weekday })
     }
 }
@@ -74,6 +84,41 @@ fn to_chrono_weekday(s: &str) -> Result<chrono::Weekday, ByDayParseError> {
     }
 }
 
+/// iCalendar token of `weekday`, the inverse of `to_chrono_weekday`.
+pub(crate) fn weekday_to_ical(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Sun => "SU",
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+    }
+}
+
+/// BYDAY value, for example `MO,WE,FR` or `-1SU`.
+impl Display for ByDay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ByDay::Simple(weekdays) => f.write_str(
+                &weekdays
+                    .iter()
+                    .map(|weekday| weekday_to_ical(*weekday))
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            ByDay::Delta(delta) => delta.fmt(f),
+        }
+    }
+}
+
+impl Display for Delta {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.delta, weekday_to_ical(self.weekday))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -89,4 +134,49 @@ mod tests {
         let _: ByDay = "-20MO".parse().unwrap();
         let _: ByDay = "30FR".parse().unwrap();
     }
+
+    #[test]
+    fn weekday_round_trip() {
+        for token in ["SU", "MO", "TU", "WE", "TH", "FR", "SA"] {
+            assert_eq!(weekday_to_ical(to_chrono_weekday(token).unwrap()), token);
+        }
+        assert!(to_chrono_weekday("XX").is_err());
+    }
+
+    #[test]
+    fn parse_empty() {
+        for s in ["", ",,"] {
+            assert!(
+                matches!(s.parse::<ByDay>(), Err(ByDayParseError::Empty)),
+                "{s:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_invalid_delta() {
+        for s in ["0MO", "60MO", "-54SU", "+0FR"] {
+            assert!(
+                matches!(
+                    s.parse::<ByDay>(),
+                    Err(ByDayParseError::DeltaOutOfRange { .. })
+                ),
+                "{s}"
+            );
+        }
+        assert!(matches!("53MO".parse::<ByDay>(), Ok(ByDay::Delta(_))));
+        assert!(matches!(
+            "XXMO".parse::<ByDay>(),
+            Err(ByDayParseError::InvalidDelta(_))
+        ));
+    }
+
+    #[test]
+    fn display_round_trip() {
+        for s in ["MO,WE,FR", "SU", "1MO", "-1SU", "-20MO"] {
+            let by_day: ByDay = s.parse().unwrap();
+            assert_eq!(by_day.to_string(), s);
+            assert_eq!(by_day.to_string().parse::<ByDay>().unwrap(), by_day);
+        }
+    }
 }
@@ -0,0 +1,74 @@
+use crate::{block::Block, ical_line_parser::split_value, VEvent, VTimezone};
+
+/// A `VTODO` component. Only the identifying properties are interpreted, the others are kept
+/// verbatim in `properties`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VTodo {
+    pub uid: Option<String>,
+    /// Still TEXT escaped, see [`ComponentRef`].
+    pub summary: Option<String>,
+    pub properties: Vec<String>,
+}
+
+/// A `VJOURNAL` component. Only the identifying properties are interpreted, the others are
+/// kept verbatim in `properties`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VJournal {
+    pub uid: Option<String>,
+    /// Still TEXT escaped, see [`ComponentRef`].
+    pub summary: Option<String>,
+    pub properties: Vec<String>,
+}
+
+/// Any of the components of a [`crate::VCalendar`], see [`crate::VCalendar::components`].
+///
+/// Whatever the component, SUMMARY is kept TEXT escaped as written (`\,`, `\n`...), like
+/// [`VEvent::summary`].
+#[derive(Debug, Clone, Copy)]
+pub enum ComponentRef<'a> {
+    Event(&'a VEvent),
+    Todo(&'a VTodo),
+    Journal(&'a VJournal),
+    Timezone(&'a VTimezone),
+}
+
+/// UID and SUMMARY of a block, along with all its property lines.
+fn identifying_properties(block: Block) -> (Option<String>, Option<String>, Vec<String>) {
+    let mut uid = None;
+    let mut summary = None;
+
+    for line in &block.inner_lines {
+        let idx_name_end = line.find([';', ':']).unwrap_or(line.len());
+        let value = split_value(line).map(|(_, value)| value);
+
+        match line[..idx_name_end].to_ascii_uppercase().as_str() {
+            "UID" => uid = value.map(str::to_owned),
+            "SUMMARY" => summary = value.map(str::to_owned),
+            _ => {}
+        }
+    }
+
+    (uid, summary, block.inner_lines)
+}
+
+impl From<Block> for VTodo {
+    fn from(block: Block) -> Self {
+        let (uid, summary, properties) = identifying_properties(block);
+        Self {
+            uid,
+            summary,
+            properties,
+        }
+    }
+}
+
+impl From<Block> for VJournal {
+    fn from(block: Block) -> Self {
+        let (uid, summary, properties) = identifying_properties(block);
+        Self {
+            uid,
+            summary,
+            properties,
+        }
+    }
+}
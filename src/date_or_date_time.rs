@@ -1,8 +1,16 @@
-use crate::by_day::{ByDay, Delta};
-use chrono::{DateTime, Datelike, Duration, LocalResult, TimeZone, Timelike, Utc, Weekday};
+use crate::{
+    by_day::{ByDay, Delta},
+    vevent::string_to_date_or_datetime,
+};
+use chrono::{
+    DateTime, Datelike, Duration, LocalResult, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc,
+    Weekday,
+};
 use std::{
     cmp::Ordering,
+    fmt::Display,
     ops::{Add, Sub},
+    str::FromStr,
 };
 use thiserror::Error;
 
@@ -10,12 +18,29 @@ use thiserror::Error;
 pub enum SubstitutionError {
     #[error("Cannot construct a date time variant by substituting a Whole day")]
     ConstructingDateTimeBySubstitutingWholeDay,
+    #[error("The substitution results in an invalid date or time")]
+    InvalidDateTime,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum DateOrDateTimeParseError {
+    #[error("Invalid DATE {value:?}, expected YYYYMMDD")]
+    InvalidDate { value: String },
+    #[error("Invalid DATE-TIME {value:?}, expected YYYYMMDDTHHMMSS, optionally followed by Z")]
+    InvalidDateTime { value: String },
 }
 
+/// A whole day, an instant, or a floating time.
+///
+/// Floating times (no trailing `Z` and no `TZID`, RFC 5545 3.3.5) are kept as the wall
+/// clock value they were written with. When they have to be compared with, or
+/// intersected against, the other variants they are treated as if that wall clock
+/// value were UTC, so the result does not depend on the host timezone.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum DateOrDateTime {
     WholeDay(DateTime<Utc>),
     DateTime(DateTime<Utc>),
+    Floating(NaiveDateTime),
 }
 
 #[derive(Error, Debug)]
@@ -35,6 +60,20 @@ pub enum EventOverlap {
 }
 
 impl DateOrDateTime {
+    /// The current instant.
+    pub fn now() -> Self {
+        DateOrDateTime::DateTime(Utc::now())
+    }
+
+    /// The current (UTC) day.
+    pub fn today() -> Self {
+        let now = Utc::now();
+        DateOrDateTime::WholeDay(
+            Utc.with_ymd_and_hms(now.year(), now.month(), now.day(), 0, 0, 0)
+                .unwrap(),
+        )
+    }
+
     pub fn substitute(
         self,
         year: Option<i32>,
@@ -53,7 +92,8 @@ impl DateOrDateTime {
                 0,
                 0,
             )
-            .unwrap();
+            .single()
+            .ok_or(SubstitutionError::InvalidDateTime)?;
 
         Ok(match self {
             DateOrDateTime::WholeDay(_) => {
@@ -62,16 +102,17 @@ impl DateOrDateTime {
                 }
                 DateOrDateTime::WholeDay(date)
             }
-            DateOrDateTime::DateTime(dt) => DateOrDateTime::DateTime(
+            DateOrDateTime::DateTime(_) | DateOrDateTime::Floating(_) => self.with_same_kind(
                 Utc.with_ymd_and_hms(
                     date.year(),
                     date.month(),
                     date.day(),
-                    hour.unwrap_or_else(|| dt.hour()),
-                    minute.unwrap_or_else(|| dt.minute()),
-                    second.unwrap_or_else(|| dt.second()),
+                    hour.unwrap_or_else(|| self.hour()),
+                    minute.unwrap_or_else(|| self.minute()),
+                    second.unwrap_or_else(|| self.second()),
                 )
-                .unwrap(),
+                .single()
+                .ok_or(SubstitutionError::InvalidDateTime)?,
             ),
         })
     }
@@ -79,6 +120,7 @@ impl DateOrDateTime {
     pub fn substitute_time_with(self, time: impl Into<DateOrDateTime>) -> Self {
         match time.into() {
             DateOrDateTime::WholeDay(_) => self,
+            floating @ DateOrDateTime::Floating(_) => floating,
             DateOrDateTime::DateTime(dt) => DateOrDateTime::DateTime(
                 Utc.with_ymd_and_hms(
                     dt.year(),
@@ -93,10 +135,12 @@ impl DateOrDateTime {
         }
     }
 
-    pub fn next_by_day(self, by_day: &ByDay) -> Self {
+    /// Next day matching `by_day`. `None` if `by_day` is a [`Delta`] that does not exist in
+    /// the month of `self` (see [`DateOrDateTime::move_by_delta`]).
+    pub fn next_by_day(self, by_day: &ByDay) -> Option<Self> {
         match by_day {
             ByDay::Delta(delta) => self.move_by_delta(delta),
-            ByDay::Simple(weekdays) => self.next_weekdays(weekdays),
+            ByDay::Simple(weekdays) => self.checked_next_weekdays(weekdays),
         }
     }
 
@@ -105,42 +149,64 @@ impl DateOrDateTime {
     }
 
     pub fn next_weekdays(self, weekdays: &[Weekday]) -> Self {
-        let mut ret = self + Duration::days(1);
+        self.checked_next_weekdays(weekdays)
+            .expect("no weekdays or date out of range")
+    }
 
-        while !weekdays
-            .iter()
-            .any(|weekday| ret.date().weekday() == *weekday)
-        {
-            ret = ret + Duration::days(1);
+    /// Next day falling on one of `weekdays`, `None` if `weekdays` is empty or the day is
+    /// out of range.
+    pub(crate) fn checked_next_weekdays(self, weekdays: &[Weekday]) -> Option<Self> {
+        let mut ret = self;
+        for _ in 0..7 {
+            ret = ret.checked_add_days(1)?;
+            if weekdays.contains(&ret.date().weekday()) {
+                return Some(ret);
+            }
         }
 
-        ret
+        None
+    }
+
+    /// Same date with the day of the month replaced, `None` if the month does not have it.
+    pub fn with_day(self, day: u32) -> Option<Self> {
+        self.substitute(None, None, Some(day), None, None, None)
+            .ok()
     }
 
-    pub fn move_by_delta(self, delta: &Delta) -> DateOrDateTime {
+    /// First day of the following month, keeping the time. `None` if out of range.
+    pub(crate) fn first_day_of_next_month(self) -> Option<Self> {
+        self.substitute(
+            Some(if self.month() == 12 {
+                self.year() + 1
+            } else {
+                self.year()
+            }),
+            Some(if self.month() == 12 {
+                1
+            } else {
+                self.month() + 1
+            }),
+            Some(1),
+            None,
+            None,
+            None,
+        )
+        .ok()
+    }
+
+    /// The `delta.delta`-th `delta.weekday` of the month of `self`, counting from the end of
+    /// the month if negative. `None` if the month does not have it (e.g. a 5th Monday in a
+    /// month with only four).
+    pub fn move_by_delta(self, delta: &Delta) -> Option<DateOrDateTime> {
+        if delta.delta == 0 {
+            return None;
+        }
+
         let month_start = self
             .substitute(None, None, Some(1), None, None, None)
             .unwrap();
 
-        let month_end = self
-            .substitute(
-                Some(if self.month() == 12 {
-                    self.year() + 1
-                } else {
-                    self.year()
-                }),
-                Some(if self.month() == 12 {
-                    1
-                } else {
-                    self.month() + 1
-                }),
-                Some(1),
-                None,
-                None,
-                None,
-            )
-            .unwrap()
-            .sub(Duration::days(1));
+        let month_end = self.first_day_of_next_month()?.sub(Duration::days(1));
 
         let mut current_delta = delta.delta.abs() - 1;
         let increment = Duration::days(delta.delta as i64 / delta.delta.abs() as i64);
@@ -150,7 +216,7 @@ impl DateOrDateTime {
             month_end
         };
 
-        loop {
+        while month_start <= current_day && current_day <= month_end {
             log::debug!(
                 "current_delta = {}, increment = {:?}, current_day = {:?}",
                 current_delta,
@@ -160,7 +226,7 @@ impl DateOrDateTime {
 
             if current_day.date().weekday() == delta.weekday {
                 if current_delta == 0 {
-                    return current_day;
+                    return Some(current_day);
                 } else {
                     current_delta -= 1;
                 }
@@ -168,91 +234,118 @@ impl DateOrDateTime {
 
             current_day = current_day + increment;
         }
+
+        None
     }
 
     pub fn equals_date(self, date: DateTime<Utc>) -> bool {
-        match self {
-            DateOrDateTime::WholeDay(d) => date == d,
-            DateOrDateTime::DateTime(dt) => date == dt,
-        }
+        self.date() == date
     }
 
     pub fn equals_date_time(self, date_time: DateTime<Utc>) -> bool {
-        match self {
-            DateOrDateTime::WholeDay(d) => d == date_time,
-            DateOrDateTime::DateTime(dt) => dt == date_time,
-        }
+        self.date() == date_time
     }
 
-    pub fn inc_month(self, increment: u32) -> Self {
-        let delta_final_months = self.month() + increment;
-        let delta_years = delta_final_months / 12;
-        let final_month = std::cmp::max(delta_final_months - delta_years * 12, 1);
-
-        let mut year = self.year() + delta_years as i32;
-        let mut month = final_month;
-        let day = self.day();
-
-        // we need to loop because some months do not have all the dates. For example, february is
-        // does not have 30,31 (and sometimes not even 29).
-        let date = {
-            let mut date = Utc.with_ymd_and_hms(year, month, day, 0, 0, 0);
-            while matches!(date, LocalResult::None) {
-                month += 1;
-                if month > 12 {
-                    month = 1;
-                    year += 1;
-                }
-
-                date = Utc.with_ymd_and_hms(year, month, day, 0, 0, 0);
+    /// Moves forward by `increment` months, keeping the day and the time. Days that do not
+    /// exist in the target month (e.g. February 30th) move on to the following month. `None`
+    /// if the result is out of range.
+    pub fn inc_month(self, increment: u32) -> Option<Self> {
+        // zero based months make the year rollover a plain division
+        let zero_based_month = (self.month() - 1).checked_add(increment)?;
+
+        let mut year = self
+            .year()
+            .checked_add(i32::try_from(zero_based_month / 12).ok()?)?;
+        let mut month = zero_based_month % 12 + 1;
+
+        // some months do not have all the dates. For example, february does not have 30,31
+        // (and sometimes not even 29): the month after always has them.
+        for _ in 0..2 {
+            if let LocalResult::Single(date) = Utc.with_ymd_and_hms(
+                year,
+                month,
+                self.day(),
+                self.hour(),
+                self.minute(),
+                self.second(),
+            ) {
+                return Some(self.with_same_kind(date));
             }
-            date.unwrap()
-        };
 
-        match self {
-            DateOrDateTime::WholeDay(_) => DateOrDateTime::WholeDay(date),
-            DateOrDateTime::DateTime(dt) => DateOrDateTime::DateTime(
-                Utc.with_ymd_and_hms(
-                    date.year(),
-                    date.month(),
-                    date.day(),
-                    dt.hour(),
-                    dt.minute(),
-                    dt.second(),
-                )
-                .unwrap(),
-            ),
+            month += 1;
+            if month > 12 {
+                month = 1;
+                year = year.checked_add(1)?;
+            }
         }
+
+        None
     }
 
-    pub fn inc_year(&self, increment: u32) -> DateOrDateTime {
-        match self {
-            DateOrDateTime::WholeDay(d) => {
-                let d = Utc
-                    .with_ymd_and_hms(d.year() + increment as i32, d.month(), d.day(), 0, 0, 0)
-                    .unwrap();
-                Self::WholeDay(d)
-            }
-            DateOrDateTime::DateTime(d) => {
-                let d = Utc
-                    .with_ymd_and_hms(
-                        d.year() + increment as i32,
-                        d.month(),
-                        d.day(),
-                        d.hour(),
-                        d.minute(),
-                        d.second(),
-                    )
-                    .unwrap();
-                Self::DateTime(d)
+    /// Moves forward by `increment` years, keeping day and month. Dates that
+    /// do not exist in the target year (February 29th) move on to the next
+    /// year where they do. `None` if the result is out of range. As with
+    /// [`Self::inc_month`], an increment of 0 leaves the date unchanged.
+    pub fn inc_year(&self, increment: u32) -> Option<DateOrDateTime> {
+        if increment == 0 {
+            return Some(*self);
+        }
+        let increment = i32::try_from(increment).ok()?;
+        let mut year = self.date().year();
+        while year <= NaiveDate::MAX.year() {
+            year = year.checked_add(increment)?;
+            let candidate = Utc
+                .with_ymd_and_hms(
+                    year,
+                    self.month(),
+                    self.day(),
+                    self.hour(),
+                    self.minute(),
+                    self.second(),
+                )
+                .single()
+                .map(|d| self.with_same_kind(d));
+            if candidate.is_some() {
+                return candidate;
             }
         }
+
+        None
     }
 
+    /// The value as a UTC instant. Floating times are read as if they were UTC.
     pub fn date(self) -> DateTime<Utc> {
         match self {
             DateOrDateTime::WholeDay(d) => d,
             DateOrDateTime::DateTime(dt) => dt,
+            DateOrDateTime::Floating(naive) => Utc.from_utc_datetime(&naive),
+        }
+    }
+
+    /// The value in the time zone `tz`. Whole days and floating times have no time zone of
+    /// their own: they keep their wall clock time, moved forward if it doesn't exist in `tz`
+    /// (daylight saving time gaps).
+    pub fn with_timezone<Tz: TimeZone>(self, tz: &Tz) -> DateTime<Tz> {
+        let naive = match self {
+            DateOrDateTime::DateTime(dt) => return dt.with_timezone(tz),
+            DateOrDateTime::WholeDay(d) => d.naive_utc(),
+            DateOrDateTime::Floating(naive) => naive,
+        };
+
+        (0..=24)
+            .find_map(|hours| {
+                tz.from_local_datetime(&(naive + Duration::hours(hours)))
+                    .earliest()
+            })
+            .unwrap_or_else(|| tz.from_utc_datetime(&naive))
+    }
+
+    /// Builds the same variant as `self` out of `date`.
+    fn with_same_kind(self, date: DateTime<Utc>) -> Self {
+        match self {
+            DateOrDateTime::WholeDay(_) => DateOrDateTime::WholeDay(date),
+            DateOrDateTime::DateTime(_) => DateOrDateTime::DateTime(date),
+            DateOrDateTime::Floating(_) => DateOrDateTime::Floating(date.naive_utc()),
         }
     }
 
@@ -260,6 +353,7 @@ impl DateOrDateTime {
         match self {
             DateOrDateTime::WholeDay(d) => d.year(),
             DateOrDateTime::DateTime(d) => d.year(),
+            DateOrDateTime::Floating(d) => d.year(),
         }
     }
 
@@ -267,6 +361,7 @@ impl DateOrDateTime {
         match self {
             DateOrDateTime::WholeDay(d) => d.month(),
             DateOrDateTime::DateTime(d) => d.month(),
+            DateOrDateTime::Floating(d) => d.month(),
         }
     }
 
@@ -274,6 +369,7 @@ impl DateOrDateTime {
         match self {
             DateOrDateTime::WholeDay(d) => d.day(),
             DateOrDateTime::DateTime(d) => d.day(),
+            DateOrDateTime::Floating(d) => d.day(),
         }
     }
 
@@ -281,6 +377,7 @@ impl DateOrDateTime {
         match self {
             DateOrDateTime::WholeDay(_d) => 0,
             DateOrDateTime::DateTime(d) => d.hour(),
+            DateOrDateTime::Floating(d) => d.hour(),
         }
     }
 
@@ -288,6 +385,7 @@ impl DateOrDateTime {
         match self {
             DateOrDateTime::WholeDay(_d) => 0,
             DateOrDateTime::DateTime(d) => d.minute(),
+            DateOrDateTime::Floating(d) => d.minute(),
         }
     }
 
@@ -295,6 +393,7 @@ impl DateOrDateTime {
         match self {
             DateOrDateTime::WholeDay(_d) => 0,
             DateOrDateTime::DateTime(d) => d.second(),
+            DateOrDateTime::Floating(d) => d.second(),
         }
     }
 
@@ -328,19 +427,10 @@ impl DateOrDateTime {
                     (Ordering::Greater, _) => Ok(EventOverlap::StartsFuture),
                 }
             }
-            DateOrDateTime::DateTime(dt) => {
-                let dt_start = match dt_start {
-                    DateOrDateTime::DateTime(dt) => dt,
-                    DateOrDateTime::WholeDay(d) => Utc
-                        .with_ymd_and_hms(d.year(), d.month(), d.day(), 0, 0, 0)
-                        .unwrap(),
-                };
-                let dt_end = match dt_end {
-                    DateOrDateTime::DateTime(dt) => dt,
-                    DateOrDateTime::WholeDay(d) => Utc
-                        .with_ymd_and_hms(d.year(), d.month(), d.day(), 0, 0, 0)
-                        .unwrap(),
-                };
+            DateOrDateTime::DateTime(_) | DateOrDateTime::Floating(_) => {
+                let dt = self.date();
+                let dt_start = dt_start.date();
+                let dt_end = dt_end.date();
 
                 match (
                     dt_start.date_naive().cmp(&dt.date_naive()),
@@ -364,45 +454,66 @@ impl DateOrDateTime {
 }
 
 impl DateOrDateTime {
-    pub fn succ_day(&self) -> DateOrDateTime {
-        match self {
-            DateOrDateTime::WholeDay(whole) => {
-                DateOrDateTime::WholeDay(*whole + chrono::Duration::days(1))
-            }
-            DateOrDateTime::DateTime(dt) => {
-                DateOrDateTime::DateTime(*dt + chrono::Duration::days(1))
+    /// Last instant covered by `dt_start..dt_end`. The end of a whole day range is exclusive
+    /// (RFC 5545, 3.6.1) so `20220101..20220102` ends on `20220101`. Timed ranges are
+    /// returned unchanged.
+    pub fn inclusive_end(dt_start: DateOrDateTime, dt_end: DateOrDateTime) -> DateOrDateTime {
+        match (dt_start, dt_end) {
+            (DateOrDateTime::WholeDay(start), DateOrDateTime::WholeDay(end)) if end > start => {
+                DateOrDateTime::WholeDay(end - chrono::Duration::days(1))
             }
+            _ => dt_end,
         }
     }
 
-    pub fn as_datetime(&self) -> DateTime<Utc> {
-        match self {
-            DateOrDateTime::WholeDay(day) => *day,
-            DateOrDateTime::DateTime(dt) => *dt,
+    /// Same as [`DateOrDateTime::intersects`], but treating `dt_end` as exclusive when both
+    /// ends are whole days, as is the case for the `DTEND` of whole day events.
+    pub fn intersects_day_aware(
+        self,
+        dt_start: DateOrDateTime,
+        dt_end: DateOrDateTime,
+    ) -> Result<EventOverlap, DateIntersectError> {
+        self.intersects(dt_start, Self::inclusive_end(dt_start, dt_end))
+    }
+
+    pub fn succ_day(&self) -> DateOrDateTime {
+        *self + chrono::Duration::days(1)
+    }
+
+    /// Adds `days`, returning `None` if the result does not fit in a `DateTime`.
+    pub fn checked_add_days(self, days: i64) -> Option<DateOrDateTime> {
+        // chrono can't represent more than ~262000 years anyway
+        if days.abs() > 365 * 300_000 {
+            return None;
         }
+
+        self.date()
+            .checked_add_signed(Duration::days(days))
+            .map(|date| self.with_same_kind(date))
+    }
+
+    /// Adds `duration`, returning `None` if the result does not fit in a `DateTime`.
+    pub fn checked_add_signed(self, duration: Duration) -> Option<DateOrDateTime> {
+        self.date()
+            .checked_add_signed(duration)
+            .map(|date| self.with_same_kind(date))
+    }
+
+    pub fn as_datetime(&self) -> DateTime<Utc> {
+        self.date()
     }
 }
 
 impl PartialOrd for DateOrDateTime {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        // convert in date time if necessary
-        let self_dt = match self {
-            DateOrDateTime::DateTime(dt) => *dt,
-            DateOrDateTime::WholeDay(dt) => *dt,
-        };
-
-        let other_dt = match other {
-            DateOrDateTime::DateTime(dt) => *dt,
-            DateOrDateTime::WholeDay(dt) => *dt,
-        };
-
-        Some(self_dt.cmp(&other_dt))
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for DateOrDateTime {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.partial_cmp(other).unwrap()
+        // convert in date time if necessary
+        self.date().cmp(&other.date())
     }
 }
 
@@ -410,17 +521,7 @@ impl Sub for DateOrDateTime {
     type Output = chrono::Duration;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        let dt_self = match self {
-            DateOrDateTime::WholeDay(d) => d,
-            DateOrDateTime::DateTime(dt) => dt,
-        };
-
-        let dt_rhs = match rhs {
-            DateOrDateTime::WholeDay(d) => d,
-            DateOrDateTime::DateTime(dt) => dt,
-        };
-
-        dt_self - dt_rhs
+        self.date() - rhs.date()
     }
 }
 
@@ -431,6 +532,7 @@ impl Add<Duration> for DateOrDateTime {
         match self {
             DateOrDateTime::WholeDay(day) => Self::WholeDay(day + rhs),
             DateOrDateTime::DateTime(dt) => Self::DateTime(dt + rhs),
+            DateOrDateTime::Floating(naive) => Self::Floating(naive + rhs),
         }
     }
 }
@@ -442,14 +544,49 @@ impl Sub<Duration> for DateOrDateTime {
         match self {
             DateOrDateTime::WholeDay(day) => Self::WholeDay(day - rhs),
             DateOrDateTime::DateTime(dt) => Self::DateTime(dt - rhs),
+            DateOrDateTime::Floating(naive) => Self::Floating(naive - rhs),
         }
     }
 }
 
+/// iCalendar representation, `YYYYMMDD` for whole days, `YYYYMMDDTHHMMSS` for floating
+/// times and `YYYYMMDDTHHMMSSZ` otherwise.
+impl Display for DateOrDateTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DateOrDateTime::WholeDay(d) => write!(f, "{}", d.format("%Y%m%d")),
+            DateOrDateTime::DateTime(dt) => write!(f, "{}", dt.format("%Y%m%dT%H%M%SZ")),
+            DateOrDateTime::Floating(naive) => write!(f, "{}", naive.format("%Y%m%dT%H%M%S")),
+        }
+    }
+}
+
+/// Parses an iCalendar DATE (`YYYYMMDD`) or DATE-TIME, either UTC (`YYYYMMDDTHHMMSSZ`) or
+/// floating (`YYYYMMDDTHHMMSS`).
+impl FromStr for DateOrDateTime {
+    type Err = DateOrDateTimeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        string_to_date_or_datetime(s)
+    }
+}
+
+impl From<DateTime<Utc>> for DateOrDateTime {
+    fn from(dt: DateTime<Utc>) -> Self {
+        DateOrDateTime::DateTime(dt)
+    }
+}
+
+/// A whole day, anchored at midnight UTC.
+impl From<NaiveDate> for DateOrDateTime {
+    fn from(date: NaiveDate) -> Self {
+        DateOrDateTime::WholeDay(Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap()))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::NaiveDateTime;
 
     #[test]
     fn inc_month_simple() {
@@ -467,15 +604,85 @@ mod tests {
             .unwrap(),
         );
 
-        let next = date.inc_month(1);
+        let next = date.inc_month(1).unwrap();
         assert_eq!(date.year(), next.year());
         assert_eq!(date.month() + 1, next.month());
 
-        let next = date_time.inc_month(12);
+        let next = date_time.inc_month(12).unwrap();
         assert_eq!(date_time.year() + 1, next.year());
         assert_eq!(date_time.month(), next.month());
     }
 
+    #[test]
+    fn inc_month_from_december() {
+        let december =
+            DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 12, 15, 10, 30, 0).unwrap());
+
+        let next = december.inc_month(1).unwrap();
+        assert_eq!((next.year(), next.month(), next.day()), (2023, 1, 15));
+
+        let next = december.inc_month(12).unwrap();
+        assert_eq!((next.year(), next.month(), next.day()), (2023, 12, 15));
+
+        let next = december.inc_month(13).unwrap();
+        assert_eq!((next.year(), next.month(), next.day()), (2024, 1, 15));
+
+        let next = december.inc_month(24).unwrap();
+        assert_eq!((next.year(), next.month(), next.day()), (2024, 12, 15));
+        assert_eq!((next.hour(), next.minute()), (10, 30));
+
+        let january_31 =
+            DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, 31, 10, 30, 0).unwrap());
+        let next = january_31.inc_month(1).unwrap();
+        assert_eq!((next.year(), next.month(), next.day()), (2022, 3, 31));
+    }
+
+    #[test]
+    fn inc_year_leap_day() {
+        let leap_day =
+            DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2024, 2, 29, 10, 30, 0).unwrap());
+        let next = leap_day.inc_year(1).unwrap();
+        assert_eq!((next.year(), next.month(), next.day()), (2028, 2, 29));
+
+        // no leap year left before the end of the range
+        let last_leap_day =
+            DateOrDateTime::DateTime(Utc.with_ymd_and_hms(262_140, 2, 29, 10, 30, 0).unwrap());
+        assert_eq!(last_leap_day.inc_year(1), None);
+
+        assert_eq!(leap_day.inc_year(300_000), None);
+        assert_eq!(leap_day.inc_year(u32::MAX), None);
+
+        // no move, like inc_month
+        assert_eq!(leap_day.inc_year(0), Some(leap_day));
+        assert_eq!(leap_day.inc_month(0), Some(leap_day));
+    }
+
+    #[test]
+    fn inc_month_out_of_range() {
+        let date = DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 12, 15, 10, 30, 0).unwrap());
+
+        assert_eq!(date.inc_month(4_000_000), None);
+        assert_eq!(date.inc_month(u32::MAX), None);
+        assert_eq!(
+            date.checked_next_weekdays(&[Weekday::Mon, Weekday::Fri])
+                .map(|next| next.day()),
+            Some(16)
+        );
+        assert_eq!(date.checked_next_weekdays(&[]), None);
+    }
+
+    #[test]
+    fn now_and_today() {
+        let now = DateOrDateTime::now();
+        assert!(matches!(now, DateOrDateTime::DateTime(_)));
+
+        let today = DateOrDateTime::today();
+        assert!(matches!(today, DateOrDateTime::WholeDay(_)));
+        assert_eq!((today.hour(), today.minute(), today.second()), (0, 0, 0));
+        assert_eq!(today.date().timestamp_subsec_nanos(), 0);
+        assert!(today <= now && now - today < Duration::days(1));
+    }
+
     #[test]
     fn next_weekday() {
         let date: DateOrDateTime = DateOrDateTime::WholeDay(
@@ -526,11 +733,20 @@ mod tests {
             .unwrap(),
         );
 
-        let first_sunday = date.move_by_delta(&Delta::new(1, Weekday::Sun));
+        let first_sunday = date.move_by_delta(&Delta::new(1, Weekday::Sun)).unwrap();
         assert_eq!(first_sunday.day(), 6);
 
-        let last_sunday = date.move_by_delta(&Delta::new(-1, Weekday::Sun));
+        let last_sunday = date.move_by_delta(&Delta::new(-1, Weekday::Sun)).unwrap();
         assert_eq!(last_sunday.day(), 27);
+
+        let fourth_monday = date.move_by_delta(&Delta::new(4, Weekday::Mon)).unwrap();
+        assert_eq!(fourth_monday.day(), 28);
+
+        // February 2022 has only four Mondays
+        assert_eq!(date.move_by_delta(&Delta::new(5, Weekday::Mon)), None);
+        assert_eq!(date.move_by_delta(&Delta::new(-5, Weekday::Mon)), None);
+        assert_eq!(date.move_by_delta(&Delta::new(6, Weekday::Fri)), None);
+        assert_eq!(date.move_by_delta(&Delta::new(0, Weekday::Fri)), None);
     }
 
     #[test]
@@ -690,6 +906,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn check_intersects_day_aware() {
+        let day = |d| DateOrDateTime::WholeDay(Utc.with_ymd_and_hms(2022, 2, d, 0, 0, 0).unwrap());
+        let at =
+            |d, h| DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 2, d, h, 0, 0).unwrap());
+
+        // whole days: the end is exclusive
+        assert_eq!(
+            day(10).intersects_day_aware(day(10), day(11)).unwrap(),
+            EventOverlap::StartSameDayEndsSameDay
+        );
+        assert_eq!(
+            day(11).intersects_day_aware(day(10), day(11)).unwrap(),
+            EventOverlap::FinishesPast
+        );
+        assert_eq!(
+            at(11, 8).intersects_day_aware(day(10), day(11)).unwrap(),
+            EventOverlap::FinishesPast
+        );
+        assert_eq!(
+            at(11, 8).intersects_day_aware(day(10), day(13)).unwrap(),
+            EventOverlap::StartsPastEndsFuture
+        );
+        assert_eq!(
+            day(12).intersects_day_aware(day(10), day(13)).unwrap(),
+            EventOverlap::StartsPastEndsSameDay
+        );
+
+        // timed: same as intersects
+        assert_eq!(
+            at(11, 8)
+                .intersects_day_aware(at(10, 8), at(11, 6))
+                .unwrap(),
+            at(11, 8).intersects(at(10, 8), at(11, 6)).unwrap()
+        );
+    }
+
     #[test]
     fn check_intersects_date_time() {
         let e: DateOrDateTime =
@@ -831,4 +1084,126 @@ mod tests {
             EventOverlap::StartSameDayEndsSameDay
         );
     }
+
+    #[test]
+    fn floating() {
+        let naive = |d, h| {
+            NaiveDate::from_ymd_opt(2022, 1, d)
+                .unwrap()
+                .and_hms_opt(h, 0, 0)
+                .unwrap()
+        };
+        let floating = DateOrDateTime::Floating(naive(6, 10));
+
+        // compared as if the wall clock value were UTC
+        let utc = DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, 6, 10, 0, 0).unwrap());
+        assert_eq!(floating.cmp(&utc), Ordering::Equal);
+        assert!(floating < utc + Duration::seconds(1));
+        assert_eq!(
+            floating
+                .intersects(
+                    DateOrDateTime::Floating(naive(6, 8)),
+                    DateOrDateTime::Floating(naive(7, 8))
+                )
+                .unwrap(),
+            EventOverlap::StartsSameDayEndsFuture
+        );
+
+        // arithmetic keeps the variant
+        assert_eq!(
+            floating + Duration::days(1),
+            DateOrDateTime::Floating(naive(7, 10))
+        );
+        assert_eq!(
+            floating.inc_month(1),
+            Some(DateOrDateTime::Floating(
+                NaiveDate::from_ymd_opt(2022, 2, 6)
+                    .unwrap()
+                    .and_hms_opt(10, 0, 0)
+                    .unwrap()
+            ))
+        );
+        assert_eq!(floating.to_string(), "20220106T100000");
+    }
+
+    #[test]
+    fn substitute_invalid_date() {
+        let date = DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, 31, 10, 0, 0).unwrap());
+
+        assert!(matches!(
+            date.substitute(None, Some(2), None, None, None, None),
+            Err(SubstitutionError::InvalidDateTime)
+        ));
+        assert!(matches!(
+            date.substitute(None, None, None, Some(24), None, None),
+            Err(SubstitutionError::InvalidDateTime)
+        ));
+        assert_eq!(date.with_day(30).unwrap().day(), 30);
+        assert!(date
+            .with_day(1)
+            .unwrap()
+            .inc_month(1)
+            .unwrap()
+            .with_day(31)
+            .is_none());
+    }
+
+    #[test]
+    fn from_chrono() {
+        let dt = Utc.with_ymd_and_hms(2022, 1, 6, 10, 0, 0).unwrap();
+        assert_eq!(DateOrDateTime::from(dt), DateOrDateTime::DateTime(dt));
+
+        let day: DateOrDateTime = NaiveDate::from_ymd_opt(2022, 1, 6).unwrap().into();
+        assert_eq!(
+            day,
+            DateOrDateTime::WholeDay(Utc.with_ymd_and_hms(2022, 1, 6, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            day.intersects(dt.into(), dt.into()).unwrap(),
+            EventOverlap::StartSameDayEndsSameDay
+        );
+    }
+
+    #[test]
+    fn display() {
+        let dt = Utc.with_ymd_and_hms(2022, 1, 6, 10, 30, 5).unwrap();
+
+        assert_eq!(DateOrDateTime::DateTime(dt).to_string(), "20220106T103005Z");
+        assert_eq!(DateOrDateTime::WholeDay(dt).to_string(), "20220106");
+        assert_eq!(
+            format!("{:?}", DateOrDateTime::WholeDay(dt)),
+            format!("WholeDay({dt:?})")
+        );
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            "20220106".parse::<DateOrDateTime>().unwrap(),
+            DateOrDateTime::WholeDay(Utc.with_ymd_and_hms(2022, 1, 6, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            "20220106T103000Z".parse::<DateOrDateTime>().unwrap(),
+            DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, 6, 10, 30, 0).unwrap())
+        );
+        assert!(matches!(
+            "20220106T103000".parse::<DateOrDateTime>(),
+            Ok(DateOrDateTime::Floating(_))
+        ));
+
+        assert_eq!(
+            "2022AB01".parse::<DateOrDateTime>(),
+            Err(DateOrDateTimeParseError::InvalidDate {
+                value: "2022AB01".to_owned()
+            })
+        );
+        let error = "20220106T25".parse::<DateOrDateTime>().unwrap_err();
+        assert_eq!(
+            error,
+            DateOrDateTimeParseError::InvalidDateTime {
+                value: "20220106T25".to_owned()
+            }
+        );
+        assert!(error.to_string().contains("expected YYYYMMDDTHHMMSS"));
+    }
 }
@@ -0,0 +1,187 @@
+use chrono::Duration;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum DurationParseError {
+    #[error("Duration must start with P {s:?}")]
+    MissingP { s: String },
+    #[error("Missing value before designator {designator:?} in {s:?}")]
+    MissingValue { s: String, designator: char },
+    #[error("Missing designator after the last value in {s:?}")]
+    MissingDesignator { s: String },
+    #[error("Missing element in duration {s:?}")]
+    MissingElement { s: String },
+    #[error("Unrecognized designator {designator:?} in {s:?}")]
+    UnrecognizedDesignator { s: String, designator: char },
+    #[error("Invalid duration value")]
+    ParseIntError(#[from] std::num::ParseIntError),
+    #[error("Duration {s:?} out of range")]
+    OutOfRange { s: String },
+}
+
+/// Parses an RFC 5545 DURATION value (3.3.6), for example `PT1H30M`, `P1W` or `-P2D`.
+pub(crate) fn parse_duration(s: &str) -> Result<Duration, DurationParseError> {
+    let (sign, rest) = match s.as_bytes().first() {
+        Some(b'-') => (-1, &s[1..]),
+        Some(b'+') => (1, &s[1..]),
+        _ => (1, s),
+    };
+    let rest = rest
+        .strip_prefix('P')
+        .ok_or_else(|| DurationParseError::MissingP { s: s.to_owned() })?;
+
+    let out_of_range = || DurationParseError::OutOfRange { s: s.to_owned() };
+    let mut seconds: i64 = 0;
+    let mut in_time = false;
+    let mut number_start = 0;
+    // elements since the start, or since T once in the time part
+    let mut elements = 0;
+
+    for (idx, c) in rest.char_indices() {
+        if c.is_ascii_digit() {
+            continue;
+        }
+        if c == 'T' {
+            in_time = true;
+            number_start = idx + 1;
+            elements = 0;
+            continue;
+        }
+
+        let number = &rest[number_start..idx];
+        if number.is_empty() {
+            return Err(DurationParseError::MissingValue {
+                s: s.to_owned(),
+                designator: c,
+            });
+        }
+        let value: i64 = number.parse()?;
+
+        let unit = match (c, in_time) {
+            ('W', false) => 7 * 24 * 60 * 60,
+            ('D', false) => 24 * 60 * 60,
+            ('H', true) => 60 * 60,
+            ('M', true) => 60,
+            ('S', true) => 1,
+            _ => {
+                return Err(DurationParseError::UnrecognizedDesignator {
+                    s: s.to_owned(),
+                    designator: c,
+                })
+            }
+        };
+        seconds = value
+            .checked_mul(unit)
+            .and_then(|value| seconds.checked_add(value))
+            .ok_or_else(out_of_range)?;
+        number_start = idx + 1;
+        elements += 1;
+    }
+
+    if number_start < rest.len() {
+        return Err(DurationParseError::MissingDesignator { s: s.to_owned() });
+    }
+    if elements == 0 {
+        return Err(DurationParseError::MissingElement { s: s.to_owned() });
+    }
+
+    // chrono durations are limited to i64::MAX milliseconds
+    if seconds > i64::MAX / 1000 {
+        return Err(out_of_range());
+    }
+    Ok(Duration::seconds(seconds * sign))
+}
+
+/// Formats a duration as an RFC 5545 DURATION value, the reverse of [`parse_duration`].
+/// Durations of whole weeks are written in weeks.
+pub(crate) fn format_duration(duration: Duration) -> String {
+    let sign = if duration < Duration::zero() { "-" } else { "" };
+    let total = duration.num_seconds().abs();
+
+    let (days, hours, minutes, seconds) = (
+        total / 86_400,
+        total % 86_400 / 3600,
+        total % 3600 / 60,
+        total % 60,
+    );
+
+    if total != 0 && total % (7 * 86_400) == 0 {
+        return format!("{sign}P{}W", days / 7);
+    }
+
+    let mut s = format!("{sign}P");
+    if days != 0 {
+        s.push_str(&format!("{days}D"));
+    }
+    if hours != 0 || minutes != 0 || seconds != 0 || days == 0 {
+        s.push('T');
+        if hours != 0 {
+            s.push_str(&format!("{hours}H"));
+        }
+        if minutes != 0 {
+            s.push_str(&format!("{minutes}M"));
+        }
+        if seconds != 0 || (hours == 0 && minutes == 0) {
+            s.push_str(&format!("{seconds}S"));
+        }
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(parse_duration("PT6H").unwrap(), Duration::hours(6));
+        assert_eq!(parse_duration("PT1H30M").unwrap(), Duration::minutes(90));
+        assert_eq!(parse_duration("P2W").unwrap(), Duration::days(14));
+        assert_eq!(parse_duration("P1DT12H").unwrap(), Duration::hours(36));
+        assert_eq!(parse_duration("-PT15M").unwrap(), Duration::minutes(-15));
+        assert_eq!(parse_duration("+P1D").unwrap(), Duration::days(1));
+    }
+
+    #[test]
+    fn format() {
+        for (duration, s) in [
+            (Duration::hours(6), "PT6H"),
+            (Duration::minutes(90), "PT1H30M"),
+            (Duration::days(14), "P2W"),
+            (Duration::hours(36), "P1DT12H"),
+            (Duration::minutes(-15), "-PT15M"),
+            (Duration::days(1), "P1D"),
+            (Duration::zero(), "PT0S"),
+        ] {
+            assert_eq!(format_duration(duration), s);
+            assert_eq!(parse_duration(s).unwrap(), duration);
+        }
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(parse_duration("T1H").is_err());
+        assert!(parse_duration("P1H").is_err());
+        assert!(parse_duration("PTH").is_err());
+        assert!(parse_duration("P1X").is_err());
+
+        for s in ["P", "PT", "-P", "P1DT"] {
+            assert!(
+                matches!(
+                    parse_duration(s),
+                    Err(DurationParseError::MissingElement { .. })
+                ),
+                "{s}"
+            );
+        }
+        for s in ["PT15", "P1D1", "PT1H30"] {
+            assert!(
+                matches!(
+                    parse_duration(s),
+                    Err(DurationParseError::MissingDesignator { .. })
+                ),
+                "{s}"
+            );
+        }
+    }
+}
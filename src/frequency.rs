@@ -1,4 +1,4 @@
-use std::str::FromStr;
+use std::{fmt::Display, str::FromStr};
 use thiserror::Error;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -7,6 +7,7 @@ pub enum Frequency {
     Monthly,
     Weekly,
     Daily,
+    Secondly,
 }
 
 #[derive(Error, Debug)]
@@ -24,7 +25,20 @@ impl FromStr for Frequency {
             "MONTHLY" => Ok(Frequency::Monthly),
             "WEEKLY" => Ok(Frequency::Weekly),
             "DAILY" => Ok(Frequency::Daily),
+            "SECONDLY" => Ok(Frequency::Secondly),
             _ => Err(FrequencyParseError::UnrecognizedFrequency { freq: s.to_owned() }),
         }
     }
 }
+
+impl Display for Frequency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Frequency::Yearly => "YEARLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Daily => "DAILY",
+            Frequency::Secondly => "SECONDLY",
+        })
+    }
+}
@@ -1,12 +1,56 @@
+use std::{
+    collections::VecDeque,
+    io::{BufRead, Lines},
+};
+
 #[derive(Debug, Clone)]
 pub struct ICalLineParser<'a> {
-    pub lines: &'a [&'a str],
+    pub lines: Vec<&'a str>,
     pub position: usize,
 }
 
 impl<'a> ICalLineParser<'a> {
-    pub fn new(lines: &'a [&'a str]) -> Self {
-        Self { lines, position: 0 }
+    pub fn new(lines: &[&'a str]) -> Self {
+        Self {
+            lines: lines.to_vec(),
+            position: 0,
+        }
+    }
+
+    /// Parser over the lines of `text`, split as in [`ICalLineParser::split_lines`].
+    // not `FromStr`, which can't borrow from the text
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(text: &'a str) -> Self {
+        Self {
+            lines: Self::split_lines(text),
+            position: 0,
+        }
+    }
+
+    /// Splits `text` in physical lines, accepting CRLF as mandated by RFC 5545 but also bare
+    /// LF and CR. A leading UTF-8 byte order mark, which some exporters prepend, is skipped.
+    pub fn split_lines(text: &str) -> Vec<&str> {
+        let text = text.strip_prefix('\u{FEFF}').unwrap_or(text);
+        text.split("\r\n")
+            .flat_map(|line| line.split(['\r', '\n']))
+            .collect()
+    }
+
+    /// Skips the (unfolded) lines before the first one for which `predicate` returns `true`,
+    /// so that it's the next one returned. Returns `false`, with all the lines consumed, if
+    /// there is none.
+    pub fn skip_to(&mut self, predicate: impl Fn(&str) -> bool) -> bool {
+        loop {
+            let position = self.position;
+            match self.next() {
+                Some(line) if predicate(&line) => {
+                    self.position = position;
+                    return true;
+                }
+                Some(_) => {}
+                None => return false,
+            }
+        }
     }
 }
 
@@ -23,7 +67,8 @@ impl<'a> Iterator for ICalLineParser<'a> {
             if count == 0 {
                 s = Some(line.to_owned());
                 count += 1;
-            } else if let Some(stripped) = line.strip_prefix(' ') {
+            } else if let Some(stripped) = line.strip_prefix([' ', '\t']) {
+                // folded line: RFC 5545 allows either a space or a horizontal tab
                 s = Some(s.unwrap_or_default() + stripped);
                 count += 1;
             } else {
@@ -36,3 +81,220 @@ impl<'a> Iterator for ICalLineParser<'a> {
         s
     }
 }
+
+/// Same as [`ICalLineParser`] but reading the physical lines from a [`BufRead`], so the
+/// whole text never needs to be in memory at once. Line endings are handled as in
+/// [`ICalLineParser::split_lines`].
+#[derive(Debug)]
+pub struct ICalReaderLineParser<R: BufRead> {
+    lines: Lines<R>,
+    /// Physical lines after a bare CR, which [`BufRead::lines`] does not split on.
+    split_lines: VecDeque<String>,
+    next_line: Option<String>,
+}
+
+impl<R: BufRead> ICalReaderLineParser<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            lines: reader.lines(),
+            split_lines: VecDeque::new(),
+            next_line: None,
+        }
+    }
+
+    fn next_physical_line(&mut self) -> Option<std::io::Result<String>> {
+        if let Some(line) = self.split_lines.pop_front() {
+            return Some(Ok(line));
+        }
+
+        let line = match self.lines.next()? {
+            Ok(line) => line,
+            Err(error) => return Some(Err(error)),
+        };
+        let mut lines = line.split('\r');
+        let first = lines.next().unwrap_or_default().to_owned();
+        self.split_lines.extend(lines.map(str::to_owned));
+        Some(Ok(first))
+    }
+}
+
+impl<R: BufRead> Iterator for ICalReaderLineParser<R> {
+    type Item = std::io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut s = match self.next_line.take() {
+            Some(line) => line,
+            None => match self.next_physical_line()? {
+                Ok(line) => line,
+                Err(error) => return Some(Err(error)),
+            },
+        };
+
+        while let Some(line) = self.next_physical_line() {
+            match line {
+                Ok(line) => {
+                    if let Some(stripped) = line.strip_prefix([' ', '\t']) {
+                        s.push_str(stripped);
+                    } else {
+                        self.next_line = Some(line);
+                        break;
+                    }
+                }
+                Err(error) => return Some(Err(error)),
+            }
+        }
+
+        Some(Ok(s))
+    }
+}
+
+/// Index of the first of `chars` in `s` outside double quoted sections. Parameter values
+/// containing `:`, `;` or `,` must be quoted (RFC 5545, 3.2), e.g. `CN="Doe, John"`.
+pub(crate) fn find_unquoted(s: &str, chars: &[char]) -> Option<usize> {
+    let mut quoted = false;
+    s.char_indices().find_map(|(idx, c)| {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        (!quoted && chars.contains(&c)).then_some(idx)
+    })
+}
+
+/// `s` split on `separator`, ignoring separators in double quoted sections.
+pub(crate) fn split_unquoted(s: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut rest = s;
+    while let Some(idx) = find_unquoted(rest, &[separator]) {
+        parts.push(&rest[..idx]);
+        rest = &rest[idx + separator.len_utf8()..];
+    }
+    parts.push(rest);
+    parts
+}
+
+/// Parameters and value of what follows a property name, split at the first colon outside
+/// double quotes.
+pub(crate) fn split_value(s: &str) -> Option<(&str, &str)> {
+    find_unquoted(s, &[':']).map(|idx| (&s[..idx], &s[idx + 1..]))
+}
+
+/// Value of the parameter `name` in what follows a property name, for example `en-US` for
+/// `LANGUAGE` in `LANGUAGE=en-US:Meeting`. Parameter names are case insensitive.
+pub(crate) fn param_value(s: &str, name: &str) -> Option<String> {
+    let (params, _) = split_value(s)?;
+    split_unquoted(params, ';').into_iter().find_map(|param| {
+        let (key, value) = param.trim().split_once('=')?;
+        key.eq_ignore_ascii_case(name)
+            .then(|| unquote(value.trim()).to_owned())
+    })
+}
+
+/// `line` without `prefix`, compared ignoring ASCII case as for property names, for
+/// example `BEGIN:` in `begin:VEVENT`.
+pub(crate) fn strip_prefix_ignore_case<'a>(line: &'a str, prefix: &str) -> Option<&'a str> {
+    line.get(..prefix.len())
+        .filter(|start| start.eq_ignore_ascii_case(prefix))
+        .map(|_| &line[prefix.len()..])
+}
+
+/// A parameter value without its surrounding double quotes, if any.
+pub(crate) fn unquote(s: &str) -> &str {
+    s.strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unfold_space_and_tab() {
+        let lines = [
+            "BEGIN:VEVENT",
+            "SUMMARY:A long",
+            " summary folded with a space",
+            "\t and a tab",
+            "END:VEVENT",
+        ];
+
+        let unfolded = ICalLineParser::new(&lines).collect::<Vec<_>>();
+
+        assert_eq!(
+            unfolded,
+            [
+                "BEGIN:VEVENT",
+                "SUMMARY:A longsummary folded with a space and a tab",
+                "END:VEVENT"
+            ]
+        );
+
+        let text = lines.join("\r\n");
+        let unfolded_from_reader = ICalReaderLineParser::new(text.as_bytes())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(unfolded_from_reader, unfolded);
+    }
+
+    #[test]
+    fn from_str() {
+        let text = "\u{FEFF}BEGIN:VEVENT\r\nSUMMARY:Mixed\n  line endings\rUID:1\r\nEND:VEVENT";
+
+        let unfolded = ICalLineParser::from_str(text).collect::<Vec<_>>();
+        let lines = ICalLineParser::split_lines(text);
+        assert_eq!(ICalLineParser::new(&lines).collect::<Vec<_>>(), unfolded);
+        assert_eq!(
+            unfolded,
+            [
+                "BEGIN:VEVENT",
+                "SUMMARY:Mixed line endings",
+                "UID:1",
+                "END:VEVENT"
+            ]
+        );
+
+        // the BOM is left to the caller, see `VCalendar::from_reader`
+        let unfolded_from_reader = ICalReaderLineParser::new(&text.as_bytes()[3..])
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(unfolded_from_reader, unfolded);
+    }
+
+    #[test]
+    fn quoted_parameters() {
+        let line = r#"CN="Doe, John";X-NOTE="a:b;c":mailto:john@example.com"#;
+
+        let (params, value) = split_value(line).unwrap();
+        assert_eq!(value, "mailto:john@example.com");
+        assert_eq!(
+            split_unquoted(params, ';'),
+            [r#"CN="Doe, John""#, r#"X-NOTE="a:b;c""#]
+        );
+        assert_eq!(unquote(r#""Doe, John""#), "Doe, John");
+        assert_eq!(unquote("plain"), "plain");
+        assert_eq!(find_unquoted(r#""a,b",c"#, &[',']), Some(5));
+    }
+
+    #[test]
+    fn skip_to() {
+        let lines = [
+            "BEGIN:VCALENDAR",
+            "PRODID:-//Example//EN",
+            "BEGIN:VTIMEZONE",
+            "TZID:Europe/Rome",
+            "END:VTIMEZONE",
+            "BEGIN:VEVENT",
+            "SUMMARY:First",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ];
+
+        let mut parser = ICalLineParser::new(&lines);
+        assert!(parser.skip_to(|line| line == "BEGIN:VEVENT"));
+        assert_eq!(parser.next().as_deref(), Some("BEGIN:VEVENT"));
+        assert_eq!(parser.next().as_deref(), Some("SUMMARY:First"));
+
+        assert!(!parser.skip_to(|line| line == "BEGIN:VEVENT"));
+        assert_eq!(parser.next(), None);
+    }
+}
@@ -1,18 +1,32 @@
+mod attachment;
 mod block;
 mod by_day;
+mod component;
 mod date_or_date_time;
+mod duration;
 mod frequency;
 mod ical_line_parser;
+mod occurrence_stream;
+mod parse_options;
 mod rrule;
+mod text;
 mod tzid_date_time;
 mod vcalendar;
 mod vevent;
+mod vevent_builder;
 mod vevent_iterator;
 mod vtimezone;
 
+pub use attachment::*;
+pub use block::{Block, BlockParseError};
+pub use component::*;
 pub use date_or_date_time::*;
+pub use frequency::*;
+pub use ical_line_parser::{ICalLineParser, ICalReaderLineParser};
+pub use parse_options::*;
 pub use rrule::*;
 pub use tzid_date_time::*;
 pub use vcalendar::*;
 pub use vevent::*;
+pub use vevent_builder::*;
 pub use vtimezone::*;
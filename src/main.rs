@@ -1,25 +1,5 @@
-#![feature(iter_advance_by)]
-
-mod block;
-mod by_day;
-mod date_or_date_time;
-mod frequency;
-mod ical_line_parser;
-mod rrule;
-pub mod tzid_date_time;
-mod vcalendar;
-mod vevent;
-mod vevent_iterator;
-mod vtimezone;
-
-use crate::ical_line_parser::ICalLineParser;
-use block::Block;
-use chrono::{DateTime, Datelike, Local, TimeZone, Utc};
-pub use date_or_date_time::*;
-use std::collections::HashMap;
-pub use tzid_date_time::*;
-pub use vcalendar::*;
-pub use vevent::*;
+use chrono::{DateTime, Local, TimeZone, Utc};
+use ical_rust::*;
 
 fn main() {
     let e: DateOrDateTime =
@@ -40,8 +20,9 @@ fn main() {
         EventOverlap::StartsPastEndsSameDay
     );
 
-    let whole_file = std::fs::read_to_string("/home/mindflavor/tmp/basic.ics.1").unwrap();
-    //let contents = whole_file.split("\r\n").collect::<Vec<_>>();
+    let cal = VCalendar::from_file("/home/mindflavor/tmp/basic.ics.1").unwrap();
+    println!("summary == {:?}", cal.summary());
+    //let contents = ICalLineParser::split_lines(&whole_file);
     //let ical_lines: &[String] = &ICalLineParser::new(&contents).collect::<Vec<_>>();
     ////println!("ical_lines == {:?}", ical_lines);
 
@@ -64,7 +45,6 @@ fn main() {
     //    .filter(|b| b.name == "VTIMEZONE")
     //    .for_each(|b| println!("b == {b:?}"));
 
-    let cal: VCalendar = whole_file.as_str().try_into().unwrap();
     //println!("\n cal== {cal:?}\n");
 
     //let v_calendar = VCalendar::try_from(contents).unwrap();
@@ -110,7 +90,7 @@ fn main() {
     ////    RRule::Generic(_) => true,
     ////    _ => false,
     ////}) {
-    ////    println!("item == {:?}", item);
+    ////    println!("item == {} ({} events)", item.0.raw(), item.1);
     ////}
 
     ////for item in v_calendar.events.iter().filter(|i| i.dt_end.is_none()) {
@@ -149,7 +129,7 @@ fn main() {
     //item.next_occurrence_since(dt).unwrap();
 
     // find occurrences tomorrow!
-    let dt = DateOrDateTime::DateTime(Utc::now());
+    let dt = DateOrDateTime::now();
     println!("\n\tdt == {dt:?}");
 
     for event in cal.events.iter() {
@@ -158,12 +138,7 @@ fn main() {
             match next_occurrence.event_overlap {
                 EventOverlap::StartsFuture | EventOverlap::FinishesPast => continue,
                 _ => {
-                    let a = match next_occurrence.occurrence.start {
-                        DateOrDateTime::DateTime(dt) => dt,
-                        DateOrDateTime::WholeDay(wd) => Utc
-                            .with_ymd_and_hms(wd.year(), wd.month(), wd.day(), 0, 0, 0)
-                            .unwrap(),
-                    };
+                    let a = next_occurrence.occurrence.start.date();
                     let local = a.with_timezone(&Local);
 
                     println!(
@@ -0,0 +1,143 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+    ops::Range,
+};
+
+use crate::{date_or_date_time::DateOrDateTime, vevent_iterator::VEventIterator, VEvent};
+
+/// Occurrences of many events merged in chronological order. On the same day whole day
+/// occurrences come before timed ones, then timed occurrences are sorted by start time. Ties
+/// are broken by the position of the event in the source list.
+#[derive(Debug, Clone)]
+pub struct OccurrenceStream<'a> {
+    events: Vec<&'a VEvent>,
+    iterators: Vec<VEventIterator<'a>>,
+    heap: BinaryHeap<Reverse<HeapItem>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct HeapItem {
+    occurrence: Range<DateOrDateTime>,
+    index: usize,
+}
+
+impl HeapItem {
+    fn sort_key(&self) -> (chrono::NaiveDate, bool, DateOrDateTime, usize) {
+        let start = self.occurrence.start;
+        (
+            start.date().date_naive(),
+            !matches!(start, DateOrDateTime::WholeDay(_)),
+            start,
+            self.index,
+        )
+    }
+}
+
+impl PartialOrd for HeapItem {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for HeapItem {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sort_key().cmp(&other.sort_key())
+    }
+}
+
+impl<'a> OccurrenceStream<'a> {
+    pub(crate) fn new(events: impl IntoIterator<Item = &'a VEvent>) -> Self {
+        Self::with_iterators(events, |event| event.into_iter())
+    }
+
+    /// Merges the occurrences yielded by `iterator` for each of the `events`, e.g. to start
+    /// them all from a given date.
+    pub(crate) fn with_iterators(
+        events: impl IntoIterator<Item = &'a VEvent>,
+        iterator: impl Fn(&'a VEvent) -> VEventIterator<'a>,
+    ) -> Self {
+        let events = events.into_iter().collect::<Vec<_>>();
+        let mut iterators = events.iter().map(|e| iterator(e)).collect::<Vec<_>>();
+
+        let heap = iterators
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, iterator)| {
+                iterator
+                    .next()
+                    .map(|occurrence| Reverse(HeapItem { occurrence, index }))
+            })
+            .collect();
+
+        Self {
+            events,
+            iterators,
+            heap,
+        }
+    }
+}
+
+impl<'a> Iterator for OccurrenceStream<'a> {
+    type Item = (&'a VEvent, Range<DateOrDateTime>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse(HeapItem { occurrence, index }) = self.heap.pop()?;
+
+        if let Some(next_occurrence) = self.iterators[index].next() {
+            self.heap.push(Reverse(HeapItem {
+                occurrence: next_occurrence,
+                index,
+            }));
+        }
+
+        Some((self.events[index], occurrence))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vevent::tests::parse_event;
+
+    #[test]
+    fn whole_day_first() {
+        let at_nine = parse_event(&["DTSTART:20220106T090000Z", "SUMMARY:nine"]);
+        let at_midnight = parse_event(&["DTSTART:20220106T000000Z", "SUMMARY:midnight"]);
+        let whole_day = parse_event(&["DTSTART;VALUE=DATE:20220106", "SUMMARY:whole"]);
+        let day_before = parse_event(&["DTSTART:20220105T230000Z", "SUMMARY:before"]);
+
+        let summaries = OccurrenceStream::new([&at_nine, &at_midnight, &whole_day, &day_before])
+            .map(|(event, _)| event.summary.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(summaries, ["before", "whole", "midnight", "nine"]);
+    }
+
+    #[test]
+    fn merges_recurring() {
+        let daily = parse_event(&[
+            "DTSTART:20220106T090000Z",
+            "RRULE:FREQ=DAILY;COUNT=3",
+            "SUMMARY:daily",
+        ]);
+        let whole_day = parse_event(&[
+            "DTSTART;VALUE=DATE:20220107",
+            "RRULE:FREQ=DAILY;COUNT=2",
+            "SUMMARY:whole",
+        ]);
+
+        let occurrences = OccurrenceStream::new([&daily, &whole_day]).collect::<Vec<_>>();
+
+        assert_eq!(
+            occurrences
+                .iter()
+                .map(|(event, _)| event.summary.as_str())
+                .collect::<Vec<_>>(),
+            ["daily", "whole", "daily", "whole", "daily"]
+        );
+        assert!(occurrences
+            .windows(2)
+            .all(|w| w[0].1.start.date().date_naive() <= w[1].1.start.date().date_naive()));
+    }
+}
@@ -0,0 +1,55 @@
+/// How strictly calendars are parsed, see [`crate::VCalendar::parse_with`].
+///
+/// In both modes a VEVENT without `DTSTART` is an error, a missing `DTEND` defaults to
+/// `DTSTART`, a missing `SEQUENCE` to 0, missing `CREATED` and `LAST-MODIFIED` to `DTSTAMP`
+/// and a missing `SUMMARY` to the empty string, as none of them is required by RFC 5545.
+///
+/// In strict mode:
+/// * a VEVENT without `UID` or `DTSTAMP` is a `MissingMandatoryField` error;
+/// * a `TZID` unknown to `chrono_tz` in `DTSTART`, `DTEND`, `RECURRENCE-ID`, `EXDATE` or
+///   `RDATE` is an `UnknownTimeZone` error;
+/// * a `DTSTAMP`, `CREATED` or `LAST-MODIFIED` not in UTC is a `NotUtc` error;
+/// * `DTEND` together with `DURATION` is an `EndAndDuration` error;
+/// * components other than VEVENT, VTODO, VJOURNAL and VTIMEZONE are an
+///   `UnsupportedTagError`.
+///
+/// In lenient mode, the default since many real world feeds omit required properties:
+/// * `UID` is left empty and `DTSTAMP` defaults to `DTSTART`;
+/// * values with an unknown `TZID` are read as floating times, see
+///   [`crate::VCalendar::validate_timezones`];
+/// * `DTSTAMP`, `CREATED` and `LAST-MODIFIED` not in UTC are read as UTC;
+/// * `DURATION` is ignored when `DTEND` is given too;
+/// * unsupported components are skipped.
+///
+/// Independently of the mode, `trim_values` strips the spaces around TEXT values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Enforce RFC 5545 validation instead of parsing on a best effort basis.
+    pub strict: bool,
+    /// Strip leading and trailing spaces from `SUMMARY`, `DESCRIPTION` and `COMMENT`. Off by
+    /// default: spaces after the colon are part of the value, although many producers add
+    /// them by mistake (`SUMMARY: Meeting`).
+    pub trim_values: bool,
+}
+
+impl ParseOptions {
+    pub fn strict() -> Self {
+        Self {
+            strict: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn lenient() -> Self {
+        Self {
+            strict: false,
+            ..Default::default()
+        }
+    }
+
+    /// Sets [`ParseOptions::trim_values`].
+    pub fn trim_values(mut self, trim_values: bool) -> Self {
+        self.trim_values = trim_values;
+        self
+    }
+}
@@ -4,6 +4,7 @@ use crate::{
     frequency::{Frequency, FrequencyParseError},
     string_to_date_or_datetime,
 };
+use chrono::{Datelike, Duration};
 use std::str::FromStr;
 use thiserror::Error;
 
@@ -20,11 +21,15 @@ pub enum RRuleParseError {
     #[error("ParseIntError")]
     ParseIntError(#[from] std::num::ParseIntError),
     #[error("ParseDateOrDatetTimeError")]
-    ParseDateOrDatetTimeError(#[from] chrono::ParseError),
+    ParseDateOrDatetTimeError(#[from] crate::DateOrDateTimeParseError),
     #[error("Missing either BYDAY or BYMONTHDAY {line:?})")]
     MissingByDayOrByMonthDayError { line: String },
     #[error("Missing BYDAY {line:?})")]
     MissingByDayError { line: String },
+    #[error("INTERVAL must be a positive integer {line:?})")]
+    InvalidInterval { line: String },
+    #[error("BYSECOND values must be between 0 and 59 {line:?})")]
+    InvalidBySecond { line: String },
     #[error("ByDayParserError ({error:?}) line == {line:?}")]
     ByDayParserError {
         error: ByDayParseError,
@@ -49,9 +54,16 @@ pub trait Options: std::fmt::Debug {
 
     fn is_expired(&self, dt: DateOrDateTime) -> bool {
         log::debug!("is_expired(self == {:?}, dt == {:?}) called", self, dt);
+        // UNTIL is inclusive. A DATE UNTIL on timed occurrences includes the whole day.
         self.common_options()
             .until
-            .map(|until| dt > until)
+            .map(|until| match (dt, until) {
+                (
+                    DateOrDateTime::DateTime(_) | DateOrDateTime::Floating(_),
+                    DateOrDateTime::WholeDay(until),
+                ) => dt.date().date_naive() > until.date_naive(),
+                _ => dt > until,
+            })
             .unwrap_or(false)
     }
 }
@@ -66,8 +78,22 @@ pub enum RRule {
     WeeklyByDay(WeeklyByDay),
     Weekly(Weekly),
     Daily(Daily),
+    Secondly(Secondly),
+    /// A rule whose FREQ is known but whose combination of parts is not supported. Only
+    /// the first occurrence of events with such a rule is generated.
+    Generic(Generic),
 }
 
+const KNOWN_TOKENS: &[&str] = &[
+    "FREQ",
+    "INTERVAL",
+    "UNTIL",
+    "COUNT",
+    "BYMONTH",
+    "BYMONTHDAY",
+    "BYDAY",
+];
+
 impl FromStr for RRule {
     type Err = RRuleParseError;
 
@@ -90,6 +116,9 @@ impl FromStr for RRule {
             .map(|item| &item["INTERVAL=".len()..])
             .map(|item| item.parse())
             .transpose()?;
+        if interval == Some(0) {
+            return Err(RRuleParseError::InvalidInterval { line: s.to_owned() });
+        }
 
         let until: Option<DateOrDateTime> = tokens
             .iter()
@@ -130,76 +159,330 @@ impl FromStr for RRule {
                 line: s.to_owned(),
             })?;
 
-        Ok(match frequency {
-            Frequency::Yearly => {
-                if let Some(by_month) = by_month {
-                    if let Some(by_month_day) = by_month_day {
-                        Self::YearlyByMonthByMonthDay(YearlyByMonthByMonthDay {
-                            month: by_month,
-                            month_day: by_month_day,
-                            common_options: CommonOptions::new(s, until, interval, count),
-                        })
-                    } else if let Some(by_day) = by_day {
-                        Self::YearlyByMonthByDay(YearlyByMonthByDay {
-                            month: by_month,
-                            day: by_day,
-                            common_options: CommonOptions::new(s, until, interval, count),
-                        })
-                    } else {
-                        return Err(RRuleParseError::MissingrNextTokenAfterByMonth {
-                            line: s.to_owned(),
-                        });
+        let by_second: Option<Vec<u8>> = tokens
+            .iter()
+            .find(|item| item.starts_with("BYSECOND="))
+            .map(|item| &item["BYSECOND=".len()..])
+            .map(|s| s.split(',').map(|s| s.parse()).collect())
+            .transpose()?;
+        if by_second.iter().flatten().any(|second| *second > 59) {
+            return Err(RRuleParseError::InvalidBySecond { line: s.to_owned() });
+        }
+
+        // keep what we don't understand so it's not lost
+        let unknown_tokens = tokens
+            .iter()
+            .filter_map(|item| item.split_once('='))
+            .filter(|(key, _)| !KNOWN_TOKENS.contains(key))
+            .filter(|(key, _)| !(frequency == Frequency::Secondly && *key == "BYSECOND"))
+            .map(|(key, value)| (key.to_owned(), value.to_owned()))
+            .collect();
+
+        let common_options = CommonOptions::new(s, until, interval, count, unknown_tokens);
+
+        // parts we don't model change the occurrences, better not to guess them
+        let has_unsupported_parts = common_options
+            .unknown_tokens
+            .iter()
+            .any(|(key, _)| key.starts_with("BY"));
+        let generic = |common_options| {
+            log::warn!("unsupported RRULE {s:?}, only DTSTART will be generated");
+            Self::Generic(Generic {
+                frequency,
+                common_options,
+            })
+        };
+
+        // we ignore WKST
+        Ok(match (frequency, by_month, by_month_day, by_day) {
+            _ if has_unsupported_parts => generic(common_options),
+
+            (Frequency::Yearly, None, None, None) => Self::Yearly(Yearly { common_options }),
+            (Frequency::Yearly, Some(month), Some(month_day), None) => {
+                Self::YearlyByMonthByMonthDay(YearlyByMonthByMonthDay {
+                    month,
+                    month_day,
+                    common_options,
+                })
+            }
+            (Frequency::Yearly, Some(month), None, Some(day)) => {
+                Self::YearlyByMonthByDay(YearlyByMonthByDay {
+                    month,
+                    day,
+                    common_options,
+                })
+            }
+
+            (Frequency::Monthly, None, Some(month_day), None) => {
+                Self::MonthlyByMonthDay(MonthlyByMonthDay {
+                    month_day,
+                    common_options,
+                })
+            }
+            (Frequency::Monthly, None, None, Some(day)) => Self::MonthlyByDay(MonthlyByDay {
+                day,
+                common_options,
+            }),
+
+            (Frequency::Weekly, None, None, None) => Self::Weekly(Weekly { common_options }),
+            (Frequency::Weekly, None, None, Some(day)) => Self::WeeklyByDay(WeeklyByDay {
+                day,
+                common_options,
+            }),
+
+            (Frequency::Daily, None, None, None) => Self::Daily(Daily { common_options }),
+
+            (Frequency::Secondly, None, None, None) => Self::Secondly(Secondly {
+                by_second: by_second.unwrap_or_default(),
+                common_options,
+            }),
+
+            _ => generic(common_options),
+        })
+    }
+}
+
+impl RRule {
+    /// The `FREQ` of the rule.
+    pub fn frequency(&self) -> Frequency {
+        match self {
+            RRule::Yearly(_) | RRule::YearlyByMonthByMonthDay(_) | RRule::YearlyByMonthByDay(_) => {
+                Frequency::Yearly
+            }
+            RRule::MonthlyByMonthDay(_) | RRule::MonthlyByDay(_) => Frequency::Monthly,
+            RRule::WeeklyByDay(_) | RRule::Weekly(_) => Frequency::Weekly,
+            RRule::Daily(_) => Frequency::Daily,
+            RRule::Secondly(_) => Frequency::Secondly,
+            RRule::Generic(rrule) => rrule.frequency,
+        }
+    }
+
+    /// The rule as it was written, e.g. `FREQ=WEEKLY;BYDAY=MO,FR`.
+    pub fn raw(&self) -> &str {
+        &self.common_options().raw
+    }
+
+    /// Serializes the rule back to the `RRULE` value format, e.g. `FREQ=WEEKLY;BYDAY=MO,FR`.
+    pub fn to_rrule_string(&self) -> String {
+        // we don't know which parts matter, give back what we got
+        if let RRule::Generic(rrule) = self {
+            return rrule.common_options.raw.clone();
+        }
+
+        let mut tokens = vec![format!("FREQ={}", self.frequency())];
+
+        let common_options = self.common_options();
+        if let Some(until) = common_options.until {
+            tokens.push(format!("UNTIL={until}"));
+        }
+        if let Some(count) = common_options.count {
+            tokens.push(format!("COUNT={count}"));
+        }
+        if let Some(interval) = common_options.interval {
+            tokens.push(format!("INTERVAL={interval}"));
+        }
+
+        match self {
+            RRule::YearlyByMonthByMonthDay(rrule) => {
+                tokens.push(format!("BYMONTH={}", rrule.month));
+                tokens.push(format!("BYMONTHDAY={}", rrule.month_day));
+            }
+            RRule::YearlyByMonthByDay(rrule) => {
+                tokens.push(format!("BYMONTH={}", rrule.month));
+                tokens.push(format!("BYDAY={}", rrule.day));
+            }
+            RRule::MonthlyByMonthDay(rrule) => {
+                tokens.push(format!("BYMONTHDAY={}", rrule.month_day));
+            }
+            RRule::MonthlyByDay(rrule) => {
+                tokens.push(format!("BYDAY={}", rrule.day));
+            }
+            RRule::WeeklyByDay(rrule) => {
+                tokens.push(format!("BYDAY={}", rrule.day));
+            }
+            RRule::Secondly(rrule) => {
+                if !rrule.by_second.is_empty() {
+                    tokens.push(format!(
+                        "BYSECOND={}",
+                        rrule
+                            .by_second
+                            .iter()
+                            .map(|second| second.to_string())
+                            .collect::<Vec<_>>()
+                            .join(",")
+                    ));
+                }
+            }
+            RRule::Yearly(_) | RRule::Weekly(_) | RRule::Daily(_) | RRule::Generic(_) => {}
+        }
+
+        tokens.extend(
+            common_options
+                .unknown_tokens
+                .iter()
+                .map(|(key, value)| format!("{key}={value}")),
+        );
+
+        tokens.join(";")
+    }
+
+    /// The occurrence following `last_occurrence`, `None` once the rule is over (`UNTIL`
+    /// passed) or if it can't be expanded. `COUNT` is left to the caller, which knows how
+    /// many occurrences were generated.
+    pub(crate) fn next_occurrence(
+        &self,
+        last_occurrence: DateOrDateTime,
+    ) -> Option<DateOrDateTime> {
+        // each rule applies INTERVAL by itself
+        let interval = self.common_options().interval.unwrap_or(1);
+        let next_occurrence = match self {
+            RRule::Yearly(_) | RRule::YearlyByMonthByMonthDay(_) => {
+                last_occurrence.inc_year(interval)?
+            }
+
+            RRule::YearlyByMonthByDay(rrule) => {
+                next_by_day_in_period(last_occurrence, &rrule.day, interval.checked_mul(12)?)?
+            }
+
+            RRule::MonthlyByMonthDay(rrule) => {
+                // months without the requested day (e.g. the 31st) are skipped entirely
+                let mut month = last_occurrence.with_day(1)?;
+                let mut next_occurrence = None;
+                for _ in 0..12 {
+                    month = month.inc_month(interval)?;
+                    next_occurrence = month.with_day(rrule.month_day as u32);
+                    if next_occurrence.is_some() {
+                        break;
                     }
-                } else {
-                    // we ignore WKST
-                    Self::Yearly(Yearly {
-                        common_options: CommonOptions::new(s, until, interval, count),
-                    })
                 }
+                next_occurrence?
             }
 
-            Frequency::Monthly => {
-                if let Some(by_month_day) = by_month_day {
-                    Self::MonthlyByMonthDay(MonthlyByMonthDay {
-                        month_day: by_month_day,
-                        common_options: CommonOptions::new(s, until, interval, count),
-                    })
-                } else if let Some(by_day) = by_day {
-                    Self::MonthlyByDay(MonthlyByDay {
-                        day: by_day,
-                        common_options: CommonOptions::new(s, until, interval, count),
-                    })
-                } else {
-                    return Err(RRuleParseError::MissingByDayOrByMonthDayError {
-                        line: s.to_owned(),
-                    });
+            RRule::MonthlyByDay(rrule) => {
+                next_by_day_in_period(last_occurrence, &rrule.day, interval)?
+            }
+
+            RRule::Weekly(_) => last_occurrence.checked_add_days(7 * interval as i64)?,
+
+            RRule::WeeklyByDay(rrule) => {
+                let mut next_occurrence = last_occurrence.next_by_day(&rrule.day)?;
+                // moving to another week skips the weeks excluded by INTERVAL (weeks start on
+                // Monday, WKST is not supported)
+                if interval > 1 && week_start(next_occurrence) != week_start(last_occurrence) {
+                    next_occurrence =
+                        next_occurrence.checked_add_days(7 * (interval as i64 - 1))?;
                 }
+                next_occurrence
             }
 
-            Frequency::Weekly => {
-                if let Some(day) = by_day {
-                    Self::WeeklyByDay(WeeklyByDay {
-                        day,
-                        common_options: CommonOptions::new(s, until, interval, count),
-                    })
-                } else {
-                    Self::Weekly(Weekly {
-                        common_options: CommonOptions::new(s, until, interval, count),
-                    })
+            RRule::Daily(_) => last_occurrence.checked_add_days(interval as i64)?,
+
+            RRule::Secondly(rrule) => {
+                // every INTERVAL-th second, limited to BYSECOND. After 60 steps the seconds of
+                // the minute repeat, so there is no need to look further.
+                let step = Duration::seconds(interval as i64);
+                let mut next_occurrence = last_occurrence;
+                let mut matching = None;
+                for _ in 0..60 {
+                    next_occurrence = next_occurrence.checked_add_signed(step)?;
+                    if rrule.by_second.is_empty()
+                        || rrule
+                            .by_second
+                            .iter()
+                            .any(|second| *second as u32 == next_occurrence.second())
+                    {
+                        matching = Some(next_occurrence);
+                        break;
+                    }
                 }
+                matching?
             }
 
-            Frequency::Daily => Self::Daily(Daily {
-                common_options: CommonOptions::new(s, until, interval, count),
-            }),
+            RRule::Generic(rrule) => {
+                log::warn!(
+                    "expansion of RRULE {:?} is not supported, only DTSTART is generated",
+                    rrule.common_options().raw
+                );
+                return None;
+            }
+        };
+        log::debug!(
+            "last_occurrence == {:?}, next_occurrence == {:?}",
+            last_occurrence,
+            next_occurrence
+        );
+
+        (!self.is_expired(next_occurrence)).then_some(next_occurrence)
+    }
+
+    /// Expands the rule starting at `dt_start`, which is always the first occurrence, as
+    /// for the `DTSTART` of an event. `COUNT` and `UNTIL` are honored, rules without either
+    /// end only once the dates get out of chrono's range.
+    pub fn occurrences(
+        &self,
+        dt_start: DateOrDateTime,
+    ) -> impl Iterator<Item = DateOrDateTime> + '_ {
+        let count = self
+            .common_options()
+            .count
+            .map_or(usize::MAX, |count| count as usize);
+        std::iter::successors(Some(dt_start), move |last_occurrence| {
+            self.next_occurrence(*last_occurrence)
         })
+        .take(count)
     }
 }
 
+/// Monday of the week of `date`.
+fn week_start(date: DateOrDateTime) -> chrono::NaiveDate {
+    let date = date.date().date_naive();
+    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+/// Next day matching `by_day` after `last`, in the month of `last` or, if there is none,
+/// in the month `months` months later. Months without the requested day (e.g. a 5th Monday)
+/// are skipped, looking at most 12 periods ahead.
+fn next_by_day_in_period(
+    last: DateOrDateTime,
+    by_day: &ByDay,
+    months: u32,
+) -> Option<DateOrDateTime> {
+    if let ByDay::Simple(weekdays) = by_day {
+        // every matching weekday of the month is an occurrence on its own
+        let next = last.checked_next_weekdays(weekdays)?;
+        if (next.year(), next.month()) == (last.year(), last.month()) {
+            return Some(next);
+        }
+    }
+
+    let mut month = last.with_day(1)?;
+    for _ in 0..12 {
+        month = month.inc_month(months)?;
+        let next = match by_day {
+            ByDay::Simple(weekdays) => {
+                let first = if weekdays.contains(&month.date().weekday()) {
+                    month
+                } else {
+                    month.checked_next_weekdays(weekdays)?
+                };
+                (first.month() == month.month()).then_some(first)
+            }
+            // Calculate 1SU or -1SU... done in DateOrDatetime
+            ByDay::Delta(delta) => month.move_by_delta(delta),
+        };
+        if next.is_some() {
+            return next;
+        }
+    }
+
+    None
+}
+
+/// See [`RRule::Generic`]. The rule as written is in `common_options.raw`.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Generic {
     pub frequency: Frequency,
-    pub raw: String,
+    pub common_options: CommonOptions,
 }
 
 #[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
@@ -208,6 +491,8 @@ pub struct CommonOptions {
     pub until: Option<DateOrDateTime>,
     pub interval: Option<u32>,
     pub count: Option<u32>,
+    /// `KEY=VALUE` parts not otherwise interpreted (for example `WKST`), in order.
+    pub unknown_tokens: Vec<(String, String)>,
 }
 
 impl CommonOptions {
@@ -216,12 +501,14 @@ impl CommonOptions {
         until: Option<DateOrDateTime>,
         interval: Option<u32>,
         count: Option<u32>,
+        unknown_tokens: Vec<(String, String)>,
     ) -> Self {
         Self {
             raw: raw.into(),
             until,
             interval,
             count,
+            unknown_tokens,
         }
     }
 }
@@ -273,6 +560,19 @@ pub struct Daily {
     pub common_options: CommonOptions,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Secondly {
+    /// Seconds of the minute (`BYSECOND`) the rule is limited to. Empty means every second.
+    pub by_second: Vec<u8>,
+    pub common_options: CommonOptions,
+}
+
+impl Options for Generic {
+    fn common_options(&self) -> &CommonOptions {
+        &self.common_options
+    }
+}
+
 impl Options for Yearly {
     fn common_options(&self) -> &CommonOptions {
         &self.common_options
@@ -321,6 +621,12 @@ impl Options for Daily {
     }
 }
 
+impl Options for Secondly {
+    fn common_options(&self) -> &CommonOptions {
+        &self.common_options
+    }
+}
+
 impl Options for RRule {
     fn common_options(&self) -> &CommonOptions {
         match self {
@@ -332,6 +638,228 @@ impl Options for RRule {
             RRule::WeeklyByDay(rrule) => &rrule.common_options,
             RRule::Weekly(rrule) => &rrule.common_options,
             RRule::Daily(rrule) => &rrule.common_options,
+            RRule::Secondly(rrule) => &rrule.common_options,
+            RRule::Generic(rrule) => &rrule.common_options,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    #[test]
+    fn unknown_tokens_round_trip() {
+        let rrule: RRule = "FREQ=WEEKLY;X-FOO=bar;BYDAY=MO,FR;WKST=SU".parse().unwrap();
+        assert!(matches!(rrule, RRule::WeeklyByDay(_)));
+        assert_eq!(rrule.raw(), "FREQ=WEEKLY;X-FOO=bar;BYDAY=MO,FR;WKST=SU");
+
+        assert_eq!(
+            rrule.common_options().unknown_tokens,
+            [
+                ("X-FOO".to_owned(), "bar".to_owned()),
+                ("WKST".to_owned(), "SU".to_owned())
+            ]
+        );
+        assert_eq!(
+            rrule.to_rrule_string(),
+            "FREQ=WEEKLY;BYDAY=MO,FR;X-FOO=bar;WKST=SU"
+        );
+    }
+
+    #[test]
+    fn to_rrule_string() {
+        for s in [
+            "FREQ=DAILY",
+            "FREQ=DAILY;UNTIL=20220110T100000Z;INTERVAL=2",
+            "FREQ=MONTHLY;UNTIL=20220110;BYDAY=-1SU",
+            "FREQ=MONTHLY;COUNT=10;BYMONTHDAY=15",
+            "FREQ=YEARLY;BYMONTH=3;BYMONTHDAY=8",
+            "FREQ=SECONDLY;COUNT=4;BYSECOND=0,30",
+        ] {
+            let rrule: RRule = s.parse().unwrap();
+            assert_eq!(rrule.to_rrule_string(), s);
+            assert_eq!(rrule.to_rrule_string().parse::<RRule>().unwrap(), rrule);
+        }
+    }
+
+    #[test]
+    fn invalid_by_second() {
+        assert!(matches!(
+            "FREQ=SECONDLY;BYSECOND=0,60".parse::<RRule>(),
+            Err(RRuleParseError::InvalidBySecond { .. })
+        ));
+    }
+
+    #[test]
+    fn invalid_interval() {
+        for rule in [
+            "FREQ=DAILY;INTERVAL=0",
+            "FREQ=MONTHLY;BYMONTHDAY=1;INTERVAL=0",
+        ] {
+            assert!(matches!(
+                rule.parse::<RRule>(),
+                Err(RRuleParseError::InvalidInterval { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn frequency() {
+        for (rule, frequency) in [
+            ("FREQ=YEARLY", Frequency::Yearly),
+            ("FREQ=YEARLY;BYMONTH=3;BYMONTHDAY=1", Frequency::Yearly),
+            ("FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU", Frequency::Yearly),
+            ("FREQ=MONTHLY;BYMONTHDAY=1", Frequency::Monthly),
+            ("FREQ=MONTHLY;BYDAY=1MO", Frequency::Monthly),
+            ("FREQ=WEEKLY", Frequency::Weekly),
+            ("FREQ=WEEKLY;BYDAY=MO", Frequency::Weekly),
+            ("FREQ=DAILY", Frequency::Daily),
+            ("FREQ=SECONDLY;BYSECOND=0", Frequency::Secondly),
+        ] {
+            assert_eq!(rule.parse::<RRule>().unwrap().frequency(), frequency);
+        }
+    }
+
+    #[test]
+    fn generic_fallback() {
+        let raw = "FREQ=MONTHLY;BYSETPOS=-1;COUNT=3";
+        let rrule: RRule = raw.parse().unwrap();
+
+        assert!(matches!(rrule, RRule::Generic(_)));
+        assert_eq!(rrule.raw(), raw);
+        assert_eq!(rrule.frequency(), Frequency::Monthly);
+        assert_eq!(rrule.common_options().count, Some(3));
+        assert_eq!(rrule.to_rrule_string(), raw);
+
+        for rule in [
+            "FREQ=YEARLY;BYMONTH=3",
+            "FREQ=YEARLY;BYDAY=MO",
+            "FREQ=YEARLY;BYMONTHDAY=1",
+            "FREQ=YEARLY;BYMONTH=3;BYMONTHDAY=1;BYDAY=MO",
+            "FREQ=MONTHLY;BYMONTHDAY=1;BYDAY=MO",
+            "FREQ=MONTHLY;BYMONTH=3;BYDAY=MO",
+            "FREQ=WEEKLY;BYMONTH=3",
+            "FREQ=DAILY;BYDAY=MO,TU",
+            "FREQ=DAILY;BYHOUR=9,17",
+            "FREQ=WEEKLY;BYDAY=MO;BYMINUTE=30",
+            "FREQ=YEARLY;BYYEARDAY=100",
+            "FREQ=YEARLY;BYWEEKNO=20",
+            "FREQ=SECONDLY;BYDAY=MO",
+        ] {
+            let rrule: RRule = rule.parse().unwrap();
+            assert!(matches!(rrule, RRule::Generic(_)), "{rule}");
+            assert_eq!(rrule.to_rrule_string(), rule);
+        }
+    }
+
+    #[test]
+    fn by_set_pos_only_dtstart() {
+        // the last weekday of the month, not five consecutive days
+        let start = DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, 31, 10, 0, 0).unwrap());
+        let rrule: RRule = "FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1;COUNT=5"
+            .parse()
+            .unwrap();
+
+        assert!(matches!(rrule, RRule::Generic(_)));
+        assert_eq!(rrule.occurrences(start).collect::<Vec<_>>(), [start]);
+    }
+
+    #[test]
+    fn occurrences() {
+        use chrono::{TimeZone, Utc};
+        let day = |d| DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, d, 10, 0, 0).unwrap());
+
+        let rrule: RRule = "FREQ=WEEKLY;BYDAY=MO,WE;COUNT=4".parse().unwrap();
+        assert_eq!(
+            rrule.occurrences(day(3)).collect::<Vec<_>>(),
+            [day(3), day(5), day(10), day(12)]
+        );
+
+        let rrule: RRule = "FREQ=DAILY;INTERVAL=2;UNTIL=20220109T100000Z"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            rrule.occurrences(day(3)).collect::<Vec<_>>(),
+            [day(3), day(5), day(7), day(9)]
+        );
+
+        let rrule: RRule = "FREQ=MONTHLY;BYDAY=-1SU".parse().unwrap();
+        assert_eq!(rrule.occurrences(day(30)).nth(12), day(29).inc_year(1));
+
+        // only the start of unsupported rules
+        let rrule: RRule = "FREQ=MONTHLY;BYSETPOS=-1".parse().unwrap();
+        assert_eq!(rrule.occurrences(day(3)).collect::<Vec<_>>(), [day(3)]);
+    }
+
+    #[test]
+    fn huge_interval() {
+        // the last Thursday of the month
+        let start = DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, 27, 10, 0, 0).unwrap());
+        let last_day = DateOrDateTime::DateTime(
+            NaiveDate::MAX
+                .and_hms_opt(10, 0, 0)
+                .unwrap()
+                .and_local_timezone(Utc)
+                .unwrap(),
+        );
+
+        for rule in [
+            "FREQ=YEARLY",
+            "FREQ=YEARLY;BYMONTH=1;BYMONTHDAY=27",
+            "FREQ=YEARLY;BYMONTH=1;BYDAY=-1TH",
+            "FREQ=MONTHLY;BYMONTHDAY=6",
+            "FREQ=MONTHLY;BYDAY=1TH",
+            "FREQ=MONTHLY;BYDAY=TH",
+            "FREQ=WEEKLY",
+            "FREQ=WEEKLY;BYDAY=TH",
+            "FREQ=DAILY",
+            "FREQ=SECONDLY;BYSECOND=0",
+        ] {
+            for interval in [4_000_000, 300_000_000, u32::MAX] {
+                let rrule: RRule = format!("{rule};INTERVAL={interval}").parse().unwrap();
+                // either a later occurrence or none at all, never a panic or a loop
+                assert!(
+                    rrule.next_occurrence(start).is_none_or(|next| next > start),
+                    "{rule} {interval}"
+                );
+            }
+
+            // one step past the last representable day
+            let rrule: RRule = rule.parse().unwrap();
+            if rrule.frequency() != Frequency::Secondly {
+                assert_eq!(rrule.next_occurrence(last_day), None, "{rule}");
+            }
+        }
+    }
+
+    #[test]
+    fn occurrences_extreme_interval() {
+        let start = DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, 27, 10, 0, 0).unwrap());
+
+        for rule in [
+            "FREQ=YEARLY",
+            "FREQ=YEARLY;BYMONTH=2;BYMONTHDAY=29",
+            "FREQ=YEARLY;BYMONTH=1;BYDAY=-1TH",
+            "FREQ=MONTHLY;BYMONTHDAY=31",
+            "FREQ=MONTHLY;BYDAY=5TH",
+            "FREQ=WEEKLY",
+            "FREQ=WEEKLY;BYDAY=TH,FR",
+            "FREQ=DAILY",
+        ] {
+            for interval in [4_000_000, u32::MAX] {
+                let rrule: RRule = format!("{rule};INTERVAL={interval};COUNT={}", u32::MAX)
+                    .parse()
+                    .unwrap();
+                assert!(rrule.occurrences(start).count() < 100, "{rule} {interval}");
+            }
+        }
+
+        // every ~136 years until the end of the range
+        let rrule: RRule = format!("FREQ=SECONDLY;INTERVAL={}", u32::MAX)
+            .parse()
+            .unwrap();
+        assert_eq!(rrule.occurrences(start).count(), 1_912);
+    }
+}
@@ -0,0 +1,61 @@
+/// Reverts the TEXT escaping of RFC 5545 (3.3.11): `\n`/`\N` become new lines and `\\`, `\;`
+/// and `\,` the escaped character.
+pub(crate) fn unescape_text(s: &str) -> String {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') | Some('N') => unescaped.push('\n'),
+                Some(escaped) => unescaped.push(escaped),
+                None => unescaped.push('\\'),
+            }
+        } else {
+            unescaped.push(c);
+        }
+    }
+
+    unescaped
+}
+
+/// Escapes `s` as an RFC 5545 TEXT value, the reverse of [`unescape_text`].
+pub(crate) fn escape_text(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unescape() {
+        assert_eq!(unescape_text("plain"), "plain");
+        assert_eq!(
+            unescape_text(r"a\, b\; c\\d\nline\Nline"),
+            "a, b; c\\d\nline\nline"
+        );
+        assert_eq!(unescape_text("trailing\\"), "trailing\\");
+    }
+
+    #[test]
+    fn escape() {
+        let s = "a, b; c\\d\nline";
+
+        assert_eq!(escape_text(s), r"a\, b\; c\\d\nline");
+        assert_eq!(unescape_text(&escape_text(s)), s);
+    }
+}
@@ -3,7 +3,12 @@ use chrono_tz::Tz;
 use std::{fmt::Debug, str::FromStr};
 use thiserror::Error;
 
-use crate::DateOrDateTime;
+use crate::{
+    duration::{parse_duration, DurationParseError},
+    ical_line_parser::{param_value, split_unquoted, split_value, unquote},
+    vevent::{string_to_date_or_datetime, string_to_naive_datetime},
+    DateOrDateTime,
+};
 
 #[derive(Error, Debug)]
 pub enum TzIdDateTimeFormatError {
@@ -11,14 +16,67 @@ pub enum TzIdDateTimeFormatError {
     ParseIntError(#[from] chrono::ParseError),
     #[error("Ambiguous timezone")]
     AmbiguousTimeZone,
-    #[error("Missing TZID= token")]
-    MissingTZIDToken,
+    #[error("Date parse error")]
+    DateOrDateTimeParseError(#[from] crate::DateOrDateTimeParseError),
+    #[error("Unknown TZID {tzid:?}")]
+    UnknownTimeZone { tzid: String },
+    #[error("Unsupported VALUE={value_type}")]
+    UnsupportedValueType { value_type: String },
+    #[error("Invalid PERIOD {period:?}, expected start/end or start/duration")]
+    InvalidPeriod { period: String },
+    #[error("Invalid PERIOD duration")]
+    DurationParseError(#[from] DurationParseError),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct TzIdDateTime {
     pub time_zone: Tz,
     pub date_time: DateOrDateTime,
+    /// The `TZID` parameter as written, if any. It's kept even when it names no timezone
+    /// known to `chrono_tz`, and `date_time` was read as floating time.
+    pub tzid: Option<String>,
+}
+
+impl TzIdDateTime {
+    /// Parses a comma separated list of values sharing the same parameters, as found in
+    /// `EXDATE`, for example `VALUE=DATE:20220101,20220108`.
+    pub fn parse_list(line: &str) -> Result<Vec<Self>, TzIdDateTimeFormatError> {
+        let (params, values) = match split_value(line) {
+            Some((params, values)) => (Some(params), values),
+            None => (None, line),
+        };
+
+        values
+            .split(',')
+            .map(|value| match params {
+                Some(params) => format!("{params}:{value}").parse(),
+                None => value.parse(),
+            })
+            .collect()
+    }
+
+    /// What follows the property name in an iCalendar line: the parameters, if any, and
+    /// the value. For example `;TZID=Europe/Rome:20220106T154000`, `;VALUE=DATE:20220101`,
+    /// `:20220106T154000Z` for UTC (there is no UTC VTIMEZONE to refer to) or
+    /// `:20220106T154000` for floating times.
+    pub(crate) fn to_ical_string(&self) -> String {
+        match self.date_time {
+            DateOrDateTime::WholeDay(d) => format!(";VALUE=DATE:{}", d.format("%Y%m%d")),
+            DateOrDateTime::DateTime(_) if self.time_zone == chrono_tz::UTC => {
+                format!(":{}", self.date_time)
+            }
+            DateOrDateTime::DateTime(dt) => format!(
+                ";TZID={}:{}",
+                self.tzid.as_deref().unwrap_or(self.time_zone.name()),
+                dt.with_timezone(&self.time_zone).format("%Y%m%dT%H%M%S")
+            ),
+            // an unknown zone was read as floating time
+            DateOrDateTime::Floating(_) => match &self.tzid {
+                Some(tzid) => format!(";TZID={tzid}:{}", self.date_time),
+                None => format!(":{}", self.date_time),
+            },
+        }
+    }
 }
 
 impl FromStr for TzIdDateTime {
@@ -34,6 +92,7 @@ impl<T: TimeZone> From<DateTime<T>> for TzIdDateTime {
         Self {
             time_zone: chrono_tz::UTC,
             date_time: DateOrDateTime::DateTime(dt.with_timezone(&Utc)),
+            tzid: None,
         }
     }
 }
@@ -42,41 +101,178 @@ impl TryFrom<&str> for TzIdDateTime {
     type Error = TzIdDateTimeFormatError;
 
     fn try_from(line: &str) -> Result<Self, Self::Error> {
-        if let Some(line) = line.strip_prefix("TZID=") {
-            let mut tokens = line.split(':');
+        // parameters (TZID=, VALUE=) can come in any order before the colon
+        let (params, value) = split_value(line).unwrap_or(("", line));
 
-            let tz: Tz = tokens.next().unwrap().parse().unwrap();
+        let mut time_zone = None;
+        let mut tzid = None;
+        let mut is_whole_day = false;
 
-            let date_time = tokens.next().unwrap();
+        // some producers add stray spaces around the values
+        let value = value.trim();
 
-            let date_time = NaiveDateTime::parse_from_str(date_time, "%Y%m%dT%H%M%S")?;
+        // parameter names and enumerated values are case insensitive
+        for param in split_unquoted(params, ';').into_iter() {
+            let Some((name, param_value)) = param.trim().split_once('=') else {
+                continue;
+            };
+            let param_value = unquote(param_value.trim());
+
+            if name.eq_ignore_ascii_case("TZID") {
+                tzid = Some(param_value.to_owned());
+                time_zone = Some(param_value.parse::<Tz>().map_err(|_| {
+                    TzIdDateTimeFormatError::UnknownTimeZone {
+                        tzid: param_value.to_owned(),
+                    }
+                })?);
+            } else if name.eq_ignore_ascii_case("VALUE") {
+                if param_value.eq_ignore_ascii_case("DATE") {
+                    is_whole_day = true;
+                } else if !param_value.eq_ignore_ascii_case("DATE-TIME") {
+                    return Err(TzIdDateTimeFormatError::UnsupportedValueType {
+                        value_type: param_value.to_owned(),
+                    });
+                }
+            }
+        }
+
+        if is_whole_day {
+            let date = Utc.from_utc_datetime(&NaiveDateTime::parse_from_str(
+                &format!("{value}T000000"),
+                "%Y%m%dT%H%M%S",
+            )?);
+            Ok(Self {
+                time_zone: time_zone.unwrap_or(chrono_tz::UTC),
+                date_time: DateOrDateTime::WholeDay(date),
+                tzid,
+            })
+        } else if let Some(tz) = time_zone {
+            let date_time = string_to_naive_datetime(value)?;
 
             if let LocalResult::Single(d) = tz.from_local_datetime(&date_time) {
                 Ok(Self {
                     time_zone: tz,
                     date_time: DateOrDateTime::DateTime(d.with_timezone(&Utc)),
+                    tzid,
                 })
             } else {
                 Err(TzIdDateTimeFormatError::AmbiguousTimeZone)
             }
-        } else if let Some(line) = line.strip_prefix("VALUE=DATE:") {
-            let date = Utc.from_utc_datetime(&NaiveDateTime::parse_from_str(
-                &format!("{line}T000000"),
-                "%Y%m%dT%H%M%S",
-            )?);
+        } else {
+            // no TZID: either UTC (trailing Z) or floating time
             Ok(Self {
                 time_zone: chrono_tz::UTC,
-                date_time: DateOrDateTime::WholeDay(date),
+                date_time: string_to_date_or_datetime(value)?,
+                tzid: None,
             })
-        } else {
-            Err(TzIdDateTimeFormatError::MissingTZIDToken)
+        }
+    }
+}
+
+/// Value of the `TZID` parameter in what follows a property name, for example
+/// `Europe/Rome` in `VALUE=DATE-TIME;TZID=Europe/Rome:20220106T154000`.
+pub(crate) fn tzid_param(line: &str) -> Option<String> {
+    param_value(line, "TZID")
+}
+
+/// A value of `RDATE` (RFC 5545, 3.8.5.2): a date, a date time or a period.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RDate {
+    DateTime(TzIdDateTime),
+    /// An explicit period. When given as start and duration, `end` is computed from them.
+    Period {
+        start: TzIdDateTime,
+        end: TzIdDateTime,
+    },
+}
+
+impl RDate {
+    /// Start of the recurrence instance.
+    pub fn start(&self) -> DateOrDateTime {
+        match self {
+            RDate::DateTime(date_time) => date_time.date_time,
+            RDate::Period { start, .. } => start.date_time,
+        }
+    }
+
+    /// Parses a comma separated list of values sharing the same parameters, like
+    /// [`TzIdDateTime::parse_list`] but also accepting `VALUE=PERIOD`, for example
+    /// `VALUE=PERIOD:19960403T020000Z/PT2H,19960404T010000Z/19960404T030000Z`.
+    pub fn parse_list(line: &str) -> Result<Vec<Self>, TzIdDateTimeFormatError> {
+        let (params, values) = split_value(line).unwrap_or(("", line));
+
+        let mut is_period = false;
+        let params = split_unquoted(params, ';')
+            .into_iter()
+            .filter(|param| {
+                let period = param.trim().split_once('=').is_some_and(|(name, value)| {
+                    name.eq_ignore_ascii_case("VALUE") && value.eq_ignore_ascii_case("PERIOD")
+                });
+                is_period |= period;
+                !period && !param.is_empty()
+            })
+            .collect::<Vec<_>>()
+            .join(";");
+
+        if !is_period {
+            return Ok(TzIdDateTime::parse_list(line)?
+                .into_iter()
+                .map(RDate::DateTime)
+                .collect());
+        }
+
+        let parse = |value: &str| -> Result<TzIdDateTime, TzIdDateTimeFormatError> {
+            format!("{params}:{value}").parse()
+        };
+
+        values
+            .split(',')
+            .map(|period| {
+                let (start, end) = period.trim().split_once('/').ok_or_else(|| {
+                    TzIdDateTimeFormatError::InvalidPeriod {
+                        period: period.to_owned(),
+                    }
+                })?;
+                let start = parse(start)?;
+                let end = if end.trim_start_matches(['+', '-']).starts_with('P') {
+                    TzIdDateTime {
+                        time_zone: start.time_zone,
+                        tzid: start.tzid.clone(),
+                        date_time: start
+                            .date_time
+                            .checked_add_signed(parse_duration(end)?)
+                            .ok_or_else(|| TzIdDateTimeFormatError::InvalidPeriod {
+                                period: period.to_owned(),
+                            })?,
+                    }
+                } else {
+                    parse(end)?
+                };
+
+                Ok(RDate::Period { start, end })
+            })
+            .collect()
+    }
+
+    /// What follows the property name in an iCalendar line, see
+    /// [`TzIdDateTime::to_ical_string`]. Periods are written with an explicit end.
+    pub(crate) fn to_ical_string(&self) -> String {
+        match self {
+            RDate::DateTime(date_time) => date_time.to_ical_string(),
+            RDate::Period { start, end } => {
+                let end = end.to_ical_string();
+                let (_, end) = end.split_once(':').unwrap_or(("", &end));
+                format!(";VALUE=PERIOD{}/{end}", start.to_ical_string())
+            }
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::TzIdDateTime;
+    use super::TzIdDateTimeFormatError;
+    use crate::{DateOrDateTime, RDate, TzIdDateTime};
+    use chrono::{TimeZone, Utc};
 
     #[test]
     fn parse_00() {
@@ -91,4 +287,141 @@ mod tests {
 
         let _: TzIdDateTime = s.try_into().unwrap();
     }
+
+    #[test]
+    fn parse_without_tzid() {
+        let expected = DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, 1, 0, 0, 0).unwrap());
+
+        let t: TzIdDateTime = "20220101T000000Z".try_into().unwrap();
+        assert_eq!(t.time_zone, chrono_tz::UTC);
+        assert_eq!(t.date_time, expected);
+        // there is no UTC VTIMEZONE to refer to with a TZID
+        assert_eq!(t.to_ical_string(), ":20220101T000000Z");
+
+        let t: TzIdDateTime = "VALUE=DATE-TIME:20220101T000000Z".try_into().unwrap();
+        assert_eq!(t.date_time, expected);
+
+        // floating, kept as written
+        let t: TzIdDateTime = "20220101T000000".try_into().unwrap();
+        assert_eq!(
+            t.date_time,
+            DateOrDateTime::Floating(expected.date().naive_utc())
+        );
+        assert_eq!(t.to_ical_string(), ":20220101T000000");
+    }
+
+    #[test]
+    fn parse_list() {
+        let day = |d| DateOrDateTime::WholeDay(Utc.with_ymd_and_hms(2022, 1, d, 0, 0, 0).unwrap());
+
+        let list = TzIdDateTime::parse_list("VALUE=DATE:20220101,20220108,20220115").unwrap();
+        assert_eq!(
+            list.iter().map(|t| t.date_time).collect::<Vec<_>>(),
+            [day(1), day(8), day(15)]
+        );
+
+        let list =
+            TzIdDateTime::parse_list("TZID=Europe/Rome:20220106T154000,20220107T154000").unwrap();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[1].time_zone, chrono_tz::Europe::Rome);
+
+        let list = TzIdDateTime::parse_list("20220101T000000Z,20220102T000000Z").unwrap();
+        assert_eq!(list.len(), 2);
+    }
+
+    #[test]
+    fn parse_with_whitespace() {
+        let t: TzIdDateTime = "TZID= Europe/Rome :20220101T090000".try_into().unwrap();
+
+        assert_eq!(t.time_zone, chrono_tz::Europe::Rome);
+        assert_eq!(
+            t.date_time,
+            DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, 1, 8, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn parse_value_date() {
+        let s = "VALUE=DATE:20220101";
+
+        let t: TzIdDateTime = s.try_into().unwrap();
+        assert_eq!(
+            t.date_time,
+            DateOrDateTime::WholeDay(Utc.with_ymd_and_hms(2022, 1, 1, 0, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn parse_value_date_time_with_tzid() {
+        let expected =
+            DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, 6, 14, 40, 0).unwrap());
+
+        let t: TzIdDateTime = "VALUE=DATE-TIME;TZID=Europe/Rome:20220106T154000"
+            .try_into()
+            .unwrap();
+        assert_eq!(t.date_time, expected);
+
+        let t: TzIdDateTime = "TZID=Europe/Rome;VALUE=DATE-TIME:20220106T154000"
+            .try_into()
+            .unwrap();
+        assert_eq!(t.date_time, expected);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(matches!(
+            TzIdDateTime::try_from("TZID=Mars/Olympus:20220101T090000"),
+            Err(TzIdDateTimeFormatError::UnknownTimeZone { tzid }) if tzid == "Mars/Olympus"
+        ));
+        assert!(matches!(
+            TzIdDateTime::try_from("VALUE=PERIOD:20220101T090000Z/PT1H"),
+            Err(TzIdDateTimeFormatError::UnsupportedValueType { value_type }) if value_type == "PERIOD"
+        ));
+        assert!(matches!(
+            RDate::parse_list("VALUE=PERIOD:20220101T090000Z"),
+            Err(TzIdDateTimeFormatError::InvalidPeriod { .. })
+        ));
+    }
+
+    #[test]
+    fn rdate_period() {
+        let at =
+            |d, h| DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, d, h, 0, 0).unwrap());
+
+        let list = RDate::parse_list(
+            "VALUE=PERIOD:20220101T090000Z/PT2H,20220102T090000Z/20220102T120000Z",
+        )
+        .unwrap();
+        assert_eq!(list.len(), 2);
+        for (rdate, (start, end)) in list
+            .iter()
+            .zip([(at(1, 9), at(1, 11)), (at(2, 9), at(2, 12))])
+        {
+            match rdate {
+                RDate::Period { start: s, end: e } => {
+                    assert_eq!((s.date_time, e.date_time), (start, end))
+                }
+                _ => panic!("expected a period, got {rdate:?}"),
+            }
+        }
+
+        let list = RDate::parse_list("VALUE=PERIOD;TZID=Europe/Rome:20220101T100000/PT1H").unwrap();
+        assert_eq!(list[0].start(), at(1, 9));
+        assert_eq!(
+            list[0].to_ical_string(),
+            ";VALUE=PERIOD;TZID=Europe/Rome:20220101T100000/20220101T110000"
+        );
+
+        let list = RDate::parse_list("value=period:20220101T100000Z/PT1H").unwrap();
+        assert_eq!(
+            list[0].to_ical_string(),
+            ";VALUE=PERIOD:20220101T100000Z/20220101T110000Z"
+        );
+
+        let list = RDate::parse_list("VALUE=DATE:20220101,20220108").unwrap();
+        assert_eq!(
+            list[1].start(),
+            DateOrDateTime::WholeDay(Utc.with_ymd_and_hms(2022, 1, 8, 0, 0, 0).unwrap())
+        );
+    }
 }
@@ -1,14 +1,34 @@
-use crate::block::Block;
-use crate::ical_line_parser::ICalLineParser;
+use crate::block::{Block, BlockParseError};
+use crate::component::{ComponentRef, VJournal, VTodo};
+use crate::duration::{parse_duration, DurationParseError};
+use crate::ical_line_parser::{
+    split_value, strip_prefix_ignore_case, ICalLineParser, ICalReaderLineParser,
+};
+use crate::occurrence_stream::OccurrenceStream;
+use crate::parse_options::ParseOptions;
 use crate::vtimezone::{VTimezone, VTimezoneParseError};
-use crate::VEvent;
-use either::*;
+use crate::{DateOrDateTime, EventOverlap, RDate, TzIdDateTime, VEvent};
+use chrono::{TimeZone, Utc};
+use std::{
+    borrow::Borrow,
+    collections::HashSet,
+    io::BufRead,
+    ops::Range,
+    path::{Path, PathBuf},
+};
 use thiserror::Error;
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct VCalendar {
     pub timezones: Vec<VTimezone>,
     pub events: Vec<VEvent>,
+    pub todos: Vec<VTodo>,
+    pub journals: Vec<VJournal>,
+    /// Suggested polling interval for subscribed calendars (`REFRESH-INTERVAL`, RFC 7986).
+    pub refresh_interval: Option<chrono::Duration>,
+    /// iTIP method (`METHOD`, RFC 5546) such as `REQUEST` or `CANCEL`, see
+    /// [`VCalendar::apply_itip`].
+    pub method: Option<String>,
 }
 
 #[derive(Error, Debug)]
@@ -19,17 +39,437 @@ pub enum VCalendarParseError {
     UnsupportedTagError { tag: String },
     #[error("VEvent parse error")]
     VEventFormatError(#[from] crate::vevent::VEventFormatError),
+    #[error("Block parse error")]
+    BlockParseError(#[from] BlockParseError),
+    #[error("REFRESH-INTERVAL parse error")]
+    DurationParseError(#[from] DurationParseError),
+    #[error("IO error")]
+    IOError(#[from] std::io::Error),
+}
+
+/// Overview of a calendar, see [`VCalendar::summary`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CalendarSummary {
+    pub events: usize,
+    /// Events with a recurrence rule or recurrence dates.
+    pub recurring_events: usize,
+    pub todos: usize,
+    pub journals: usize,
+    pub timezones: usize,
+    /// Earliest `DTSTART` among the events, `None` without events.
+    pub earliest_start: Option<DateOrDateTime>,
+    /// Latest `DTSTART` among the events. Later occurrences of recurring events are not
+    /// considered.
+    pub latest_start: Option<DateOrDateTime>,
+}
+
+impl VCalendar {
+    /// Counts of the components of the calendar and the range of the event starts, to
+    /// quickly inspect large feeds.
+    pub fn summary(&self) -> CalendarSummary {
+        CalendarSummary {
+            events: self.events.len(),
+            recurring_events: self
+                .events
+                .iter()
+                .filter(|event| event.is_recurring())
+                .count(),
+            todos: self.todos.len(),
+            journals: self.journals.len(),
+            timezones: self.timezones.len(),
+            earliest_start: self.events.iter().map(|event| event.dt_start).min(),
+            latest_start: self.events.iter().map(|event| event.dt_start).max(),
+        }
+    }
+
+    /// Appends the events and timezones of `other`. Events sharing the same UID (and
+    /// RECURRENCE-ID, so overrides of single instances are kept apart from the master event)
+    /// are deduplicated keeping the one with the highest SEQUENCE; timezones are deduplicated
+    /// by TZID.
+    pub fn merge(&mut self, other: VCalendar) {
+        for timezone in other.timezones {
+            if !self.timezones.iter().any(|tz| tz.tz_id == timezone.tz_id) {
+                self.timezones.push(timezone);
+            }
+        }
+
+        for event in other.events {
+            add_revision(&mut self.events, event, |new, old| {
+                new.sequence > old.sequence
+            });
+        }
+
+        self.todos.extend(other.todos);
+        self.journals.extend(other.journals);
+    }
+
+    /// Applies an iTIP message (RFC 5546), such as a calendar attached to an invitation
+    /// email, according to its `METHOD`:
+    /// * `PUBLISH` and `REQUEST` add the events, or replace the ones with the same UID and
+    ///   RECURRENCE-ID if they are newer (higher SEQUENCE, or same SEQUENCE and later
+    ///   DTSTAMP);
+    /// * `ADD` adds the `DTSTART` of each event as an RDATE (a period if it lasts differently)
+    ///   of the recurring event with the same UID. Instances of unknown events are ignored;
+    /// * `CANCEL` removes the events with the same UID, unless they are newer than the
+    ///   cancellation. Cancelling a single instance (with RECURRENCE-ID) removes its override
+    ///   and excludes it from the recurring event;
+    /// * other methods, and messages without `METHOD`, are ignored.
+    pub fn apply_itip(&mut self, incoming: &VCalendar) {
+        let is_newer = |incoming: &VEvent, existing: &VEvent| {
+            (incoming.sequence, incoming.dt_stamp) > (existing.sequence, existing.dt_stamp)
+        };
+
+        match incoming.method.as_deref() {
+            Some(method @ ("PUBLISH" | "REQUEST" | "ADD")) => {
+                for timezone in &incoming.timezones {
+                    if !self.timezones.iter().any(|tz| tz.tz_id == timezone.tz_id) {
+                        self.timezones.push(timezone.clone());
+                    }
+                }
+
+                for event in &incoming.events {
+                    if method == "ADD" {
+                        add_instance(&mut self.events, event);
+                    } else {
+                        add_revision(&mut self.events, event.clone(), is_newer);
+                    }
+                }
+            }
+            Some("CANCEL") => {
+                for cancel in &incoming.events {
+                    let Some(uid) = &cancel.uid else {
+                        continue;
+                    };
+
+                    self.events.retain(|event| {
+                        event.uid.as_ref() != Some(uid)
+                            || (cancel.recurrence_id.is_some()
+                                && event.recurrence_id != cancel.recurrence_id)
+                            || is_newer(event, cancel)
+                    });
+
+                    if let Some(recurrence_id) = cancel.recurrence_id {
+                        for master in self.events.iter_mut().filter(|event| {
+                            event.uid.as_ref() == Some(uid)
+                                && event.recurrence_id.is_none()
+                                && !is_newer(event, cancel)
+                        }) {
+                            master.exdates.push(TzIdDateTime {
+                                time_zone: chrono_tz::UTC,
+                                date_time: recurrence_id,
+                                tzid: None,
+                            });
+                        }
+                    }
+                }
+            }
+            method => log::debug!("ignoring iTIP method {method:?}"),
+        }
+    }
+
+    /// The most recent revision (highest SEQUENCE) of each event, as identified by its UID
+    /// and RECURRENCE-ID, in order of first appearance. Events without UID are all returned.
+    pub fn latest_by_uid(&self) -> Vec<&VEvent> {
+        let mut latest: Vec<&VEvent> = Vec::new();
+
+        for event in &self.events {
+            add_revision(&mut latest, event, |new, old| new.sequence > old.sequence);
+        }
+
+        latest
+    }
+
+    /// All the components of the calendar: events, todos, journals and then timezones.
+    pub fn components(&self) -> impl Iterator<Item = ComponentRef<'_>> {
+        self.events
+            .iter()
+            .map(ComponentRef::Event)
+            .chain(self.todos.iter().map(ComponentRef::Todo))
+            .chain(self.journals.iter().map(ComponentRef::Journal))
+            .chain(self.timezones.iter().map(ComponentRef::Timezone))
+    }
+
+    /// Distinct timezones referenced by `TZID` in the events of the calendar, see
+    /// [`VEvent::referenced_tzids`]. Useful to check that each one has a matching
+    /// [`VTimezone`], or to drop the unused ones.
+    pub fn referenced_tzids(&self) -> HashSet<String> {
+        self.events
+            .iter()
+            .flat_map(VEvent::referenced_tzids)
+            .map(str::to_owned)
+            .collect()
+    }
+
+    /// TZIDs referenced by the events (see [`Self::referenced_tzids`]) that are neither
+    /// defined by a [`VTimezone`] of the calendar nor known to `chrono_tz`, sorted. When
+    /// parsing leniently, the values using them are read as floating times.
+    pub fn validate_timezones(&self) -> Vec<String> {
+        let mut undefined = self
+            .referenced_tzids()
+            .into_iter()
+            .filter(|tzid| {
+                !self.timezones.iter().any(|tz| &tz.tz_id == tzid)
+                    && tzid.parse::<chrono_tz::Tz>().is_err()
+            })
+            .collect::<Vec<_>>();
+        undefined.sort();
+        undefined
+    }
+
+    /// Occurrences touching the day of `day`, expanding recurrences, for calendar grid views.
+    /// They are returned in the order of the events, then of the occurrences. Whole day
+    /// events end the day before their `DTEND`. Each event is walked from `day` (see
+    /// [`VEvent::iter_from`]).
+    pub fn events_on(&self, day: DateOrDateTime) -> Vec<(&VEvent, Range<DateOrDateTime>)> {
+        let day = DateOrDateTime::WholeDay(
+            Utc.with_ymd_and_hms(day.year(), day.month(), day.day(), 0, 0, 0)
+                .unwrap(),
+        );
+
+        self.events
+            .iter()
+            .flat_map(|event| {
+                event
+                    .iter_from(day)
+                    // occurrences come in chronological order
+                    .take_while(move |occurrence| occurrence.start.date() < day.succ_day().date())
+                    .filter(move |occurrence| {
+                        !matches!(
+                            day.intersects_day_aware(occurrence.start, occurrence.end),
+                            Ok(EventOverlap::FinishesPast | EventOverlap::StartsFuture) | Err(_)
+                        )
+                    })
+                    .map(move |occurrence| (event, occurrence))
+            })
+            .collect()
+    }
+
+    /// Occurrences overlapping `start..end`, expanding recurrences, sorted by start as in
+    /// [`Self::occurrence_stream`]. Occurrences with no duration are included if they start
+    /// within the window. Each event is walked from `start` (see [`VEvent::iter_from`]), not
+    /// from its `DTSTART`.
+    pub fn agenda_between(
+        &self,
+        start: DateOrDateTime,
+        end: DateOrDateTime,
+    ) -> Vec<(&VEvent, Range<DateOrDateTime>)> {
+        OccurrenceStream::with_iterators(&self.events, |event| event.iter_from(start).until(end))
+            .take_while(|(_, occurrence)| occurrence.start < end)
+            .filter(|(_, occurrence)| occurrence.start >= start || occurrence.end > start)
+            .collect()
+    }
+
+    /// Occurrences of all the events of the calendar, in chronological order (see
+    /// [`OccurrenceStream`]).
+    pub fn occurrence_stream(&self) -> OccurrenceStream<'_> {
+        OccurrenceStream::new(&self.events)
+    }
+
+    /// Reads and parses the calendar stored in `path`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, VCalendarParseError> {
+        let whole_text = std::fs::read_to_string(path)?;
+        whole_text.as_str().try_into()
+    }
+
+    /// Parses a calendar unfolding its lines while reading them from `reader`, without
+    /// loading the whole text in memory first: each line is added to the calendar block as
+    /// soon as it's read.
+    pub fn from_reader(reader: impl BufRead) -> Result<Self, VCalendarParseError> {
+        let mut lines =
+            ICalReaderLineParser::new(reader).map(|line| line.map_err(VCalendarParseError::from));
+        let first_line = lines.next().map(|line| {
+            line.map(|line| match line.strip_prefix('\u{FEFF}') {
+                Some(stripped) => stripped.to_owned(),
+                None => line,
+            })
+        });
+
+        Block::from_lines(first_line.into_iter().chain(lines))?.try_into()
+    }
+
+    /// Parses every `.ics` file in `dir` and merges them in a single calendar (see
+    /// [`VCalendar::merge`]). Files that cannot be read or parsed are skipped and their errors
+    /// returned along with the calendar.
+    pub fn from_dir<P: AsRef<Path>>(
+        dir: P,
+    ) -> Result<(VCalendar, Vec<(PathBuf, VCalendarParseError)>), VCalendarParseError> {
+        let mut paths = std::fs::read_dir(dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        paths.retain(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|extension| extension.eq_ignore_ascii_case("ics"))
+        });
+        // make the merge order (and so the winner between equal SEQUENCEs) deterministic
+        paths.sort();
+
+        let mut calendar = VCalendar::default();
+        let mut errors = Vec::new();
+
+        for path in paths {
+            match VCalendar::from_file(&path) {
+                Ok(other) => calendar.merge(other),
+                Err(error) => errors.push((path, error)),
+            }
+        }
+
+        Ok((calendar, errors))
+    }
 }
 
 impl TryFrom<&str> for VCalendar {
     type Error = VCalendarParseError;
 
     fn try_from(whole_text: &str) -> Result<Self, Self::Error> {
-        let contents = whole_text.split("\r\n").collect::<Vec<_>>();
-        let ical_lines: &[String] = &ICalLineParser::new(&contents).collect::<Vec<_>>();
-        let block: Block = ical_lines.try_into().unwrap();
+        Self::parse_with(whole_text, &ParseOptions::default())
+    }
+}
+
+impl VCalendar {
+    /// Parses `whole_text` with the default [`ParseOptions`]. Malformed input of any kind is
+    /// reported as an error and never panics, so this is safe to call on untrusted uploads.
+    pub fn parse(whole_text: &str) -> Result<Self, VCalendarParseError> {
+        Self::parse_with(whole_text, &ParseOptions::default())
+    }
 
-        block.try_into()
+    /// Parses `whole_text` as [`VCalendar::try_from`] does, but with the given
+    /// [`ParseOptions`] (see there for the differences between strict and lenient mode).
+    pub fn parse_with(
+        whole_text: &str,
+        options: &ParseOptions,
+    ) -> Result<Self, VCalendarParseError> {
+        Self::from_block(text_to_block(whole_text)?, options)
+    }
+
+    /// Parses each of the `VCALENDAR` documents concatenated in `whole_text`, as
+    /// [`VCalendar::parse`] does. Lines outside of them are ignored, so text without any
+    /// calendar gives an empty list.
+    pub fn parse_all(whole_text: &str) -> Result<Vec<Self>, VCalendarParseError> {
+        let ical_lines = unfold(whole_text);
+
+        let mut calendars = Vec::new();
+        let mut start = None;
+        let mut depth = 0;
+        for (idx, line) in ical_lines.iter().enumerate() {
+            if let Some(name) = strip_prefix_ignore_case(line, "BEGIN:") {
+                if depth == 0 && name.eq_ignore_ascii_case("VCALENDAR") {
+                    start = Some(idx);
+                }
+                depth += 1;
+            } else if strip_prefix_ignore_case(line, "END:").is_some() && depth > 0 {
+                depth -= 1;
+                if depth == 0 {
+                    if let Some(start) = start.take() {
+                        calendars.push(&ical_lines[start..=idx]);
+                    }
+                }
+            }
+        }
+        // as for a single calendar, the last END can be missing
+        if let Some(start) = start {
+            calendars.push(&ical_lines[start..]);
+        }
+
+        calendars
+            .into_iter()
+            .map(|lines| Self::from_block(lines.try_into()?, &ParseOptions::default()))
+            .collect()
+    }
+
+    /// Parses every component of `whole_text` it can, in lenient mode, returning the errors
+    /// of the ones it could not parse instead of failing on the first of them.
+    pub fn parse_lenient(whole_text: &str) -> (Self, Vec<VCalendarParseError>) {
+        let mut errors = Vec::new();
+
+        let calendar = match text_to_block(whole_text) {
+            Ok(block) => {
+                Self::from_block_collecting_errors(block, &ParseOptions::lenient(), &mut errors)
+            }
+            Err(error) => {
+                errors.push(error.into());
+                Self::default()
+            }
+        };
+
+        (calendar, errors)
+    }
+}
+
+/// Adds `event` to `events`, or replaces the revision of the same event (same UID and
+/// RECURRENCE-ID) if `is_newer(event, existing)`. Events without UID are always added.
+fn add_revision<E: Borrow<VEvent>>(
+    events: &mut Vec<E>,
+    event: E,
+    is_newer: impl Fn(&VEvent, &VEvent) -> bool,
+) {
+    let new = event.borrow();
+    let existing = new.uid.as_ref().and_then(|uid| {
+        events.iter().position(|e| {
+            let e = e.borrow();
+            e.uid.as_ref() == Some(uid) && e.recurrence_id == new.recurrence_id
+        })
+    });
+
+    match existing {
+        Some(index) => {
+            if is_newer(new, events[index].borrow()) {
+                events[index] = event;
+            }
+        }
+        None => events.push(event),
+    }
+}
+
+/// Adds the `DTSTART` of `instance` as an RDATE of the recurring event of `events` with the
+/// same UID, as a period if it lasts differently. Instances of unknown events are ignored.
+fn add_instance(events: &mut [VEvent], instance: &VEvent) {
+    let Some(master) = events.iter_mut().find(|event| {
+        instance.uid.is_some() && event.uid == instance.uid && event.recurrence_id.is_none()
+    }) else {
+        log::debug!("ignoring iTIP ADD for unknown event {:?}", instance.uid);
+        return;
+    };
+    if master
+        .rdates
+        .iter()
+        .any(|rdate| rdate.start() == instance.dt_start)
+    {
+        return;
+    }
+
+    let utc = |date_time| TzIdDateTime {
+        time_zone: chrono_tz::UTC,
+        date_time,
+        tzid: None,
+    };
+    master
+        .rdates
+        .push(if instance.duration() == master.duration() {
+            RDate::DateTime(utc(instance.dt_start))
+        } else {
+            RDate::Period {
+                start: utc(instance.dt_start),
+                end: utc(instance.dt_end),
+            }
+        });
+}
+
+fn unfold(whole_text: &str) -> Vec<String> {
+    ICalLineParser::from_str(whole_text).collect()
+}
+
+fn text_to_block(whole_text: &str) -> Result<Block, BlockParseError> {
+    let ical_lines: &[String] = &unfold(whole_text);
+    ical_lines.try_into()
+}
+
+impl TryFrom<&Path> for VCalendar {
+    type Error = VCalendarParseError;
+
+    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+        Self::from_file(path)
     }
 }
 
@@ -37,32 +477,1206 @@ impl TryFrom<Block> for VCalendar {
     type Error = VCalendarParseError;
 
     fn try_from(block: Block) -> Result<Self, Self::Error> {
-        let results = block
-            .inner_blocks
-            .into_iter()
-            .map(|b| match b.name.as_ref() {
+        Self::from_block(block, &ParseOptions::default())
+    }
+}
+
+impl VCalendar {
+    fn from_block(block: Block, options: &ParseOptions) -> Result<Self, VCalendarParseError> {
+        let mut errors = Vec::new();
+        let calendar = Self::from_block_collecting_errors(block, options, &mut errors);
+
+        match errors.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(calendar),
+        }
+    }
+
+    /// Parses what it can of `block`, appending to `errors` the errors of the rest.
+    fn from_block_collecting_errors(
+        block: Block,
+        options: &ParseOptions,
+        errors: &mut Vec<VCalendarParseError>,
+    ) -> Self {
+        let mut refresh_interval = None;
+        let mut method = None;
+
+        for line in block.inner_lines.iter() {
+            let idx_name_end = line.find([';', ':']).unwrap_or(line.len());
+            let value = split_value(line).map(|(_, value)| value);
+
+            if line[..idx_name_end].eq_ignore_ascii_case("REFRESH-INTERVAL") {
+                match value.map(parse_duration).transpose() {
+                    Ok(value) => refresh_interval = value,
+                    Err(error) => errors.push(error.into()),
+                }
+            } else if line[..idx_name_end].eq_ignore_ascii_case("METHOD") {
+                method = value.map(|value| value.trim().to_ascii_uppercase());
+            }
+        }
+
+        let mut timezones = Vec::new();
+        let mut events = Vec::new();
+        let mut todos = Vec::new();
+        let mut journals = Vec::new();
+
+        for b in block.inner_blocks {
+            let result = match b.name.to_ascii_uppercase().as_str() {
                 "VTIMEZONE" => VTimezone::try_from(b)
-                    .map_err(VCalendarParseError::from)
-                    .map(Left),
-                "VEVENT" => VEvent::try_from(b)
-                    .map_err(VCalendarParseError::from)
-                    .map(Right),
-                _ => Err(VCalendarParseError::UnsupportedTagError {
+                    .map(|timezone| timezones.push(timezone))
+                    .map_err(VCalendarParseError::from),
+                "VEVENT" => VEvent::from_block(b, options)
+                    .map(|event| events.push(event))
+                    .map_err(VCalendarParseError::from),
+                "VTODO" => {
+                    todos.push(VTodo::from(b));
+                    Ok(())
+                }
+                "VJOURNAL" => {
+                    journals.push(VJournal::from(b));
+                    Ok(())
+                }
+                _ if options.strict => Err(VCalendarParseError::UnsupportedTagError {
                     tag: b.name().to_owned(),
                 }),
-            })
-            .collect::<Result<Vec<_>, VCalendarParseError>>()?;
+                _ => {
+                    log::debug!("skipping unsupported component {}", b.name());
+                    Ok(())
+                }
+            };
 
-        let mut timezones = Vec::new();
-        let mut events = Vec::new();
+            if let Err(error) = result {
+                errors.push(error);
+            }
+        }
+
+        Self {
+            timezones,
+            events,
+            todos,
+            journals,
+            refresh_interval,
+            method,
+        }
+    }
+}
 
-        for result in results {
-            match result {
-                Either::Left(timezone) => timezones.push(timezone),
-                Either::Right(event) => events.push(event),
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mixed_case_property_names() {
+        let text = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VTIMEZONE",
+            "tzid:Europe/Rome",
+            "BEGIN:STANDARD",
+            "TzName:CET",
+            "tzoffsetfrom:+0200",
+            "TZOFFSETTO:+0100",
+            "dtstart:19701025T030000",
+            "END:STANDARD",
+            "END:VTIMEZONE",
+            "BEGIN:vevent",
+            "Created:20220101T000000Z",
+            "last-modified:20220101T000000Z",
+            "DtStamp:20220101T000000Z",
+            "dtstart:20220106T100000Z",
+            "Dtend;TZID=Europe/Rome:20220106T120000",
+            "Sequence:2",
+            "Summary:Mixed case",
+            "END:vevent",
+            "END:VCALENDAR",
+        ]
+        .join("\r\n");
+
+        let calendar = VCalendar::try_from(text.as_str()).unwrap();
+
+        assert_eq!(calendar.timezones.len(), 1);
+        assert_eq!(calendar.timezones[0].tz_id, "Europe/Rome");
+        assert_eq!(calendar.timezones[0].offsets[0].tz_name, "CET");
+        assert_eq!(calendar.events.len(), 1);
+        assert_eq!(calendar.events[0].summary, "Mixed case");
+        assert_eq!(calendar.events[0].sequence, 2);
+        assert_eq!(calendar.events[0].duration(), chrono::Duration::hours(1));
+    }
+
+    #[test]
+    fn line_endings() {
+        let crlf = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VEVENT",
+            "UID:1",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "SUMMARY:First",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "UID:2",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "SUMMARY:Second",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ]
+        .join("\r\n");
+
+        for text in [crlf.replace("\r\n", "\n"), crlf.replace("\r\n", "\r"), crlf] {
+            let calendar = VCalendar::try_from(text.as_str()).unwrap();
+            assert_eq!(calendar.events.len(), 2);
+            assert_eq!(calendar.events[1].summary, "Second");
+        }
+    }
+
+    #[test]
+    fn refresh_interval() {
+        let mut lines = vec![
+            "BEGIN:VCALENDAR",
+            "REFRESH-INTERVAL;VALUE=DURATION:PT6H",
+            "BEGIN:VEVENT",
+            "UID:1",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ];
+
+        let calendar = VCalendar::try_from(lines.join("\r\n").as_str()).unwrap();
+        assert_eq!(calendar.refresh_interval, Some(chrono::Duration::hours(6)));
+
+        lines.remove(1);
+        let calendar = VCalendar::try_from(lines.join("\r\n").as_str()).unwrap();
+        assert_eq!(calendar.refresh_interval, None);
+    }
+
+    #[test]
+    fn byte_order_mark() {
+        let text = [
+            "\u{FEFF}BEGIN:VCALENDAR",
+            "BEGIN:VEVENT",
+            "UID:1",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ]
+        .join("\r\n");
+
+        let calendar = VCalendar::try_from(text.as_str()).unwrap();
+        assert_eq!(calendar.events.len(), 1);
+    }
+
+    #[test]
+    fn from_file() {
+        let path =
+            std::env::temp_dir().join(format!("ical_rust_from_file_{}.ics", std::process::id()));
+        let text = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VEVENT",
+            "UID:1",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "UID:2",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ]
+        .join("\r\n");
+        std::fs::write(&path, text).unwrap();
+
+        let result = VCalendar::try_from(path.as_path());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(result.unwrap().events.len(), 2);
+
+        assert!(matches!(
+            VCalendar::from_file(&path),
+            Err(VCalendarParseError::IOError(_))
+        ));
+    }
+
+    #[test]
+    fn from_reader() {
+        let lines = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VEVENT",
+            "UID:1",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "SUMMARY:First",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "UID:2",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "SEQUENCE:0",
+            "SUMMARY:Second",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ];
+        let text = lines.join("\r\n");
+
+        let calendar = VCalendar::from_reader(std::io::BufReader::new(text.as_bytes())).unwrap();
+        assert_eq!(calendar.events.len(), 2);
+        assert_eq!(calendar.events[1].summary, "Second");
+
+        // bare LF and CR too, as when parsing text
+        for line_ending in ["\n", "\r"] {
+            let other_text = text.replace("\r\n", line_ending);
+            let other = VCalendar::from_reader(other_text.as_bytes()).unwrap();
+            assert_eq!(other, calendar, "{line_ending:?}");
+            assert_eq!(
+                VCalendar::parse(&other_text).unwrap(),
+                calendar,
+                "{line_ending:?}"
+            );
+        }
+
+        assert_eq!(calendar, VCalendar::try_from(text.as_str()).unwrap());
+        let other_text = text.replace("SEQUENCE:0", "SEQUENCE:1");
+        assert_ne!(calendar, VCalendar::try_from(other_text.as_str()).unwrap());
+    }
+
+    #[test]
+    fn from_dir() {
+        let dir = std::env::temp_dir().join(format!("ical_rust_from_dir_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let a = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VEVENT",
+            "UID:1",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "UID:2",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "SEQUENCE:0",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ];
+        let b = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VEVENT",
+            "UID:2",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "SEQUENCE:1",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "UID:3",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ];
+        std::fs::write(dir.join("a.ics"), a.join("\r\n")).unwrap();
+        std::fs::write(dir.join("b.ics"), b.join("\r\n")).unwrap();
+        std::fs::write(dir.join("c.ics"), "not a calendar").unwrap();
+        std::fs::write(dir.join("d.txt"), "ignored").unwrap();
+
+        let result = VCalendar::from_dir(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+        let (calendar, errors) = result.unwrap();
+
+        assert_eq!(calendar.events.len(), 3);
+        let event_2 = calendar
+            .events
+            .iter()
+            .find(|e| e.uid.as_deref() == Some("2"))
+            .unwrap();
+        assert_eq!(event_2.sequence, 1);
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, dir.join("c.ics"));
+    }
+
+    #[test]
+    fn components() {
+        let text = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VTIMEZONE",
+            "TZID:Europe/Rome",
+            "END:VTIMEZONE",
+            "BEGIN:VEVENT",
+            "CREATED:20220101T000000Z",
+            "LAST-MODIFIED:20220101T000000Z",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "SEQUENCE:0",
+            "SUMMARY:Event",
+            "END:VEVENT",
+            "BEGIN:VTODO",
+            "UID:todo-1",
+            "SUMMARY:Buy milk\\, eggs",
+            "END:VTODO",
+            "BEGIN:VTODO",
+            "UID:todo-2",
+            "END:VTODO",
+            "BEGIN:VJOURNAL",
+            "SUMMARY:Notes",
+            "END:VJOURNAL",
+            "END:VCALENDAR",
+        ]
+        .join("\r\n");
+
+        let calendar = VCalendar::try_from(text.as_str()).unwrap();
+        assert_eq!(calendar.todos[0].uid.as_deref(), Some("todo-1"));
+        // kept escaped, like the SUMMARY of events
+        assert_eq!(
+            calendar.todos[0].summary.as_deref(),
+            Some("Buy milk\\, eggs")
+        );
+
+        let (mut events, mut todos, mut journals, mut timezones) = (0, 0, 0, 0);
+        for component in calendar.components() {
+            match component {
+                ComponentRef::Event(_) => events += 1,
+                ComponentRef::Todo(_) => todos += 1,
+                ComponentRef::Journal(_) => journals += 1,
+                ComponentRef::Timezone(_) => timezones += 1,
             }
         }
+        assert_eq!((events, todos, journals, timezones), (1, 2, 1, 1));
+    }
+
+    #[test]
+    fn merge_keeps_highest_sequence() {
+        let parse = |sequence: u32| {
+            let text = [
+                "BEGIN:VCALENDAR",
+                "BEGIN:VEVENT",
+                "UID:meeting",
+                "DTSTAMP:20220101T000000Z",
+                "DTSTART:20220106T100000Z",
+                &format!("SEQUENCE:{sequence}"),
+                &format!("SUMMARY:Meeting rev {sequence}"),
+                "END:VEVENT",
+                "END:VCALENDAR",
+            ]
+            .join("\r\n");
+            VCalendar::try_from(text.as_str()).unwrap()
+        };
+
+        for (first, second) in [(1, 2), (2, 1)] {
+            let mut calendar = parse(first);
+            calendar.merge(parse(second));
+
+            assert_eq!(calendar.events.len(), 1);
+            assert_eq!(calendar.events[0].sequence, 2);
+            assert_eq!(calendar.events[0].summary, "Meeting rev 2");
+        }
+
+        // an override of a single instance is not a duplicate of the master event
+        let mut calendar = parse(1);
+        let mut other = parse(2);
+        other.events[0].recurrence_id = Some(other.events[0].dt_start);
+        calendar.merge(other);
+        assert_eq!(calendar.events.len(), 2);
+    }
+
+    #[test]
+    fn latest_by_uid() {
+        let mut lines = vec!["BEGIN:VCALENDAR".to_owned()];
+        for (uid, sequence) in [("a", 0), ("b", 3), ("a", 2), ("b", 1), ("a", 1)] {
+            lines.extend([
+                "BEGIN:VEVENT".to_owned(),
+                format!("UID:{uid}"),
+                "DTSTAMP:20220101T000000Z".to_owned(),
+                "DTSTART:20220106T100000Z".to_owned(),
+                format!("SEQUENCE:{sequence}"),
+                format!("SUMMARY:{uid} rev {sequence}"),
+                "END:VEVENT".to_owned(),
+            ]);
+        }
+        lines.push("END:VCALENDAR".to_owned());
+        let calendar = VCalendar::try_from(lines.join("\r\n").as_str()).unwrap();
+
+        let latest = calendar.latest_by_uid();
+        assert_eq!(
+            latest
+                .iter()
+                .map(|e| e.summary.as_str())
+                .collect::<Vec<_>>(),
+            ["a rev 2", "b rev 3"]
+        );
+    }
+
+    #[test]
+    fn missing_dtstamp() {
+        let text = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VEVENT",
+            "UID:1",
+            "DTSTART:20220106T100000Z",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ]
+        .join("\r\n");
+
+        let calendar = VCalendar::try_from(text.as_str()).unwrap();
+        assert_eq!(calendar.events[0].dt_stamp, calendar.events[0].dt_start);
+        assert_eq!(
+            VCalendar::parse_with(&text, &ParseOptions::lenient()).unwrap(),
+            calendar
+        );
+
+        assert!(matches!(
+            VCalendar::parse_with(&text, &ParseOptions::strict()),
+            Err(VCalendarParseError::VEventFormatError(
+                crate::vevent::VEventFormatError::MissingMandatoryField { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn strict_and_lenient() {
+        // no UID, CREATED, LAST-MODIFIED or SUMMARY, and an unsupported component
+        let text = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VFREEBUSY",
+            "UID:busy",
+            "END:VFREEBUSY",
+            "BEGIN:VEVENT",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ]
+        .join("\r\n");
+        let lenient = VCalendar::parse_with(&text, &ParseOptions::lenient()).unwrap();
+        assert_eq!(lenient.events.len(), 1);
+        let event = &lenient.events[0];
+        assert_eq!(event.uid, None);
+        assert_eq!(event.summary, "");
+        assert_eq!(event.dt_created, event.dt_stamp);
+        assert_eq!(event.dt_last_modified, event.dt_stamp);
+
+        let text = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VEVENT",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ]
+        .join("\r\n");
+        assert!(matches!(
+            VCalendar::parse_with(&text, &ParseOptions::strict()),
+            Err(VCalendarParseError::VEventFormatError(
+                crate::vevent::VEventFormatError::MissingMandatoryField { ref field, .. }
+            )) if field == "UID"
+        ));
+
+        let text = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VFREEBUSY",
+            "UID:busy",
+            "END:VFREEBUSY",
+            "BEGIN:VEVENT",
+            "UID:1",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "SUMMARY:Complete",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ]
+        .join("\r\n");
+        assert!(matches!(
+            VCalendar::parse_with(&text, &ParseOptions::strict()),
+            Err(VCalendarParseError::UnsupportedTagError { ref tag }) if tag == "VFREEBUSY"
+        ));
+    }
+
+    #[test]
+    fn parse_lenient() {
+        let text = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VEVENT",
+            "UID:1",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:not a date",
+            "SEQUENCE:zero",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "UID:2",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "SEQUENCE:zero",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "UID:3",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ]
+        .join("\r\n");
+
+        assert!(VCalendar::try_from(text.as_str()).is_err());
+
+        // the first two events have a bad SEQUENCE, the first one a bad DTSTART too
+        let (calendar, errors) = VCalendar::parse_lenient(&text);
+        assert_eq!(calendar.events.len(), 1);
+        assert_eq!(calendar.events[0].uid.as_deref(), Some("3"));
+        assert_eq!(errors.len(), 2);
+
+        let (calendar, errors) = VCalendar::parse_lenient("not a calendar");
+        assert_eq!(calendar, VCalendar::default());
+        assert!(matches!(
+            errors[..],
+            [VCalendarParseError::BlockParseError(_)]
+        ));
+    }
+
+    #[test]
+    fn referenced_tzids() {
+        let text = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VEVENT",
+            "UID:1",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART;TZID=Europe/Rome:20220106T100000",
+            "DTEND;TZID=Europe/Rome:20220106T110000",
+            "EXDATE;TZID=America/New_York:20220107T100000",
+            "RDATE:20220108T100000Z",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "UID:2",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "RDATE;VALUE=PERIOD;TZID=Asia/Tokyo:20220108T100000/PT1H",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ]
+        .join("\r\n");
+
+        let calendar = VCalendar::try_from(text.as_str()).unwrap();
+        assert_eq!(
+            calendar.referenced_tzids(),
+            ["Europe/Rome", "America/New_York", "Asia/Tokyo"]
+                .map(str::to_owned)
+                .into()
+        );
+    }
+
+    #[test]
+    fn validate_timezones() {
+        let text = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VTIMEZONE",
+            "TZID:Outlook Zone",
+            "END:VTIMEZONE",
+            "BEGIN:VEVENT",
+            "UID:1",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART;TZID=Europe/Rome:20220106T100000",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "UID:2",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART;TZID=Custom Zone:20220106T100000",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "UID:3",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART;TZID=Outlook Zone:20220106T100000",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ]
+        .join("\r\n");
+
+        assert!(matches!(
+            VCalendar::parse_with(&text, &ParseOptions::strict()),
+            Err(VCalendarParseError::VEventFormatError(_))
+        ));
+
+        let calendar = VCalendar::try_from(text.as_str()).unwrap();
+        assert_eq!(calendar.validate_timezones(), ["Custom Zone"]);
+        assert!(matches!(
+            calendar.events[1].dt_start,
+            DateOrDateTime::Floating(_)
+        ));
+    }
+
+    #[test]
+    fn validate_timezones_of_date_lists() {
+        let text = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VEVENT",
+            "UID:1",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "RRULE:FREQ=DAILY",
+            "EXDATE;TZID=Custom Zone:20220107T100000",
+            "RDATE;TZID=Other Zone:20220110T120000",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "UID:1",
+            "DTSTAMP:20220101T000000Z",
+            "RECURRENCE-ID;TZID=Third Zone:20220108T100000",
+            "DTSTART:20220108T110000Z",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ]
+        .join("\r\n");
+
+        let calendar = VCalendar::try_from(text.as_str()).unwrap();
+        assert_eq!(
+            calendar.validate_timezones(),
+            ["Custom Zone", "Other Zone", "Third Zone"]
+        );
+        // still read as floating times
+        assert!(matches!(
+            calendar.events[0].exdates[0].date_time,
+            DateOrDateTime::Floating(_)
+        ));
+    }
+
+    #[test]
+    fn events_on() {
+        let text = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VEVENT",
+            "UID:1",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "RRULE:FREQ=DAILY",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "UID:2",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220107T230000Z",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "UID:3",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART;VALUE=DATE:20220106",
+            "DTEND;VALUE=DATE:20220107",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ]
+        .join("\r\n");
+        let calendar = VCalendar::try_from(text.as_str()).unwrap();
+
+        let on = |d| {
+            calendar
+                .events_on(DateOrDateTime::DateTime(
+                    Utc.with_ymd_and_hms(2022, 1, d, 12, 0, 0).unwrap(),
+                ))
+                .into_iter()
+                .map(|(event, occurrence)| (event.uid.clone().unwrap(), occurrence.start.day()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(on(5), []);
+        assert_eq!(on(6), [("1".to_owned(), 6), ("3".to_owned(), 6)]);
+        assert_eq!(on(7), [("1".to_owned(), 7), ("2".to_owned(), 7)]);
+        assert_eq!(on(20), [("1".to_owned(), 20)]);
+    }
+
+    #[test]
+    fn events_on_far_from_dtstart() {
+        let text = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VEVENT",
+            "UID:1",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "DTEND:20220106T110000Z",
+            "RRULE:FREQ=DAILY",
+            "SUMMARY:Every day",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ]
+        .join("\r\n");
+        let calendar = VCalendar::try_from(text.as_str()).unwrap();
+
+        let day = DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2922, 1, 6, 12, 0, 0).unwrap());
+        let occurrences = calendar
+            .events_on(day)
+            .into_iter()
+            .map(|(_, occurrence)| occurrence.start)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            occurrences,
+            [DateOrDateTime::DateTime(
+                Utc.with_ymd_and_hms(2922, 1, 6, 10, 0, 0).unwrap()
+            )]
+        );
+    }
+
+    #[test]
+    fn agenda_between() {
+        let text = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VEVENT",
+            "UID:1",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "RRULE:FREQ=DAILY;INTERVAL=2",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "UID:2",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220107T090000Z",
+            "DTEND:20220107T120000Z",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ]
+        .join("\r\n");
+        let calendar = VCalendar::try_from(text.as_str()).unwrap();
+
+        let at =
+            |d, h| DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, d, h, 0, 0).unwrap());
+        let agenda = calendar
+            .agenda_between(at(7, 10), at(10, 10))
+            .into_iter()
+            .map(|(event, occurrence)| (event.uid.clone().unwrap(), occurrence.start))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            agenda,
+            [("2".to_owned(), at(7, 9)), ("1".to_owned(), at(8, 10))]
+        );
+    }
+
+    #[test]
+    fn agenda_between_far_from_dtstart() {
+        let text = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VEVENT",
+            "UID:secondly",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "RRULE:FREQ=SECONDLY;INTERVAL=30",
+            "SUMMARY:Every half minute",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "UID:daily",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "DTEND:20220106T110000Z",
+            "RRULE:FREQ=DAILY",
+            "SUMMARY:Every day",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ]
+        .join("\r\n");
+        let calendar = VCalendar::try_from(text.as_str()).unwrap();
+
+        let at =
+            |h, m| DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2122, 1, 6, h, m, 0).unwrap());
+        let agenda = calendar
+            .agenda_between(at(10, 30), at(10, 31))
+            .into_iter()
+            .map(|(event, occurrence)| (event.uid.clone().unwrap(), occurrence.start))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            agenda,
+            [
+                ("daily".to_owned(), at(10, 0)),
+                ("secondly".to_owned(), at(10, 30)),
+                (
+                    "secondly".to_owned(),
+                    DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2122, 1, 6, 10, 30, 30).unwrap())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn apply_itip() {
+        let message = |method: &str, events: &[(&str, u32)]| {
+            let mut lines = vec!["BEGIN:VCALENDAR".to_owned(), format!("METHOD:{method}")];
+            for (uid, sequence) in events {
+                lines.extend([
+                    "BEGIN:VEVENT".to_owned(),
+                    format!("UID:{uid}"),
+                    "DTSTAMP:20220101T000000Z".to_owned(),
+                    "DTSTART:20220106T100000Z".to_owned(),
+                    format!("SEQUENCE:{sequence}"),
+                    format!("SUMMARY:{uid} rev {sequence}"),
+                    "END:VEVENT".to_owned(),
+                ]);
+            }
+            lines.push("END:VCALENDAR".to_owned());
+            VCalendar::try_from(lines.join("\r\n").as_str()).unwrap()
+        };
+        let summaries = |calendar: &VCalendar| {
+            calendar
+                .events
+                .iter()
+                .map(|e| e.summary.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let mut calendar = message("PUBLISH", &[("1", 1), ("2", 0)]);
+        assert_eq!(message("request", &[]).method.as_deref(), Some("REQUEST"));
+
+        calendar.apply_itip(&message("REQUEST", &[("1", 0), ("2", 1), ("3", 0)]));
+        assert_eq!(summaries(&calendar), ["1 rev 1", "2 rev 1", "3 rev 0"]);
+
+        // a stale cancellation is ignored
+        calendar.apply_itip(&message("CANCEL", &[("1", 0), ("3", 0)]));
+        assert_eq!(summaries(&calendar), ["1 rev 1", "2 rev 1"]);
+
+        calendar.apply_itip(&message("REPLY", &[("4", 0)]));
+        assert_eq!(summaries(&calendar), ["1 rev 1", "2 rev 1"]);
+
+        // cancelling a single instance
+        let mut cancel = message("CANCEL", &[("2", 1)]);
+        cancel.events[0].recurrence_id = Some(cancel.events[0].dt_start);
+        calendar.apply_itip(&cancel);
+        assert_eq!(summaries(&calendar), ["1 rev 1", "2 rev 1"]);
+        assert_eq!(calendar.events[1].exdates.len(), 1);
+
+        // a stale cancellation of a single instance, arriving after a newer revision
+        let mut cancel = message("CANCEL", &[("1", 0)]);
+        cancel.events[0].recurrence_id = Some(cancel.events[0].dt_start);
+        calendar.apply_itip(&cancel);
+        assert_eq!(summaries(&calendar), ["1 rev 1", "2 rev 1"]);
+        assert!(calendar.events[0].exdates.is_empty());
+    }
+
+    #[test]
+    fn apply_itip_add() {
+        let mut calendar = VCalendar::try_from(
+            [
+                "BEGIN:VCALENDAR",
+                "BEGIN:VEVENT",
+                "UID:weekly",
+                "DTSTAMP:20220101T000000Z",
+                "DTSTART:20220106T100000Z",
+                "DTEND:20220106T110000Z",
+                "RRULE:FREQ=WEEKLY;COUNT=2",
+                "SUMMARY:Weekly",
+                "END:VEVENT",
+                "END:VCALENDAR",
+            ]
+            .join("\r\n")
+            .as_str(),
+        )
+        .unwrap();
+        let add = VCalendar::try_from(
+            [
+                "BEGIN:VCALENDAR",
+                "METHOD:ADD",
+                "BEGIN:VEVENT",
+                "UID:weekly",
+                "DTSTAMP:20220102T000000Z",
+                "DTSTART:20220108T100000Z",
+                "DTEND:20220108T110000Z",
+                "SUMMARY:Weekly",
+                "END:VEVENT",
+                "BEGIN:VEVENT",
+                "UID:weekly",
+                "DTSTAMP:20220102T000000Z",
+                "DTSTART:20220109T100000Z",
+                "DTEND:20220109T130000Z",
+                "SUMMARY:Weekly",
+                "END:VEVENT",
+                "BEGIN:VEVENT",
+                "UID:unknown",
+                "DTSTAMP:20220102T000000Z",
+                "DTSTART:20220110T100000Z",
+                "SUMMARY:Unknown",
+                "END:VEVENT",
+                "END:VCALENDAR",
+            ]
+            .join("\r\n")
+            .as_str(),
+        )
+        .unwrap();
+
+        calendar.apply_itip(&add);
+        // applying it twice adds nothing more
+        calendar.apply_itip(&add);
+
+        // the master is not replaced, the instances are added to it
+        assert_eq!(calendar.events.len(), 1);
+        assert_eq!(calendar.events[0].dt_start.day(), 6);
+        let at =
+            |d, h| DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, d, h, 0, 0).unwrap());
+        assert_eq!(
+            calendar.events[0].into_iter().collect::<Vec<_>>(),
+            [
+                at(6, 10)..at(6, 11),
+                at(8, 10)..at(8, 11),
+                at(9, 10)..at(9, 13),
+                at(13, 10)..at(13, 11)
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_never_panics() {
+        // xorshift, to have reproducible inputs without depending on a random number crate
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut random = move |below: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % below as u64) as usize
+        };
+
+        // random bytes
+        for _ in 0..500 {
+            let bytes = (0..random(200))
+                .map(|_| random(256) as u8)
+                .collect::<Vec<_>>();
+            let _ = VCalendar::parse(&String::from_utf8_lossy(&bytes));
+        }
+
+        // every combination of these properties, parameters and (often invalid) values, in an
+        // event and in a timezone, to get past the line and block structure
+        let names = [
+            "DTSTART",
+            "DTEND",
+            "DURATION",
+            "RRULE",
+            "EXDATE",
+            "RDATE",
+            "RECURRENCE-ID",
+            "DTSTAMP",
+            "SEQUENCE",
+            "PRIORITY",
+            "ATTACH",
+            "SUMMARY",
+            "DESCRIPTION",
+            "ORGANIZER",
+            "TZOFFSETFROM",
+            "TZID",
+            "REFRESH-INTERVAL",
+            "METHOD",
+        ];
+        let params = [
+            "",
+            ";TZID=Europe/Rome",
+            ";TZID=\"Unknown/Zone\"",
+            ";VALUE=DATE",
+            ";VALUE=PERIOD",
+            ";VALUE=DURATION",
+            ";ENCODING=BASE64;VALUE=BINARY",
+            ";LANGUAGE=en",
+            ";X=\"a:b;c\"",
+        ];
+        let values = [
+            "",
+            "20220106T100000Z",
+            "20220106",
+            "20220106T100000.5",
+            "2022",
+            "20220229T250000",
+            "+262143-12-31",
+            "P1W",
+            "-PT1H",
+            "P9999999999999W",
+            "PT99999999999999999999S",
+            "FREQ=DAILY;COUNT=3",
+            "FREQ=MONTHLY;BYDAY=1€",
+            "FREQ=MONTHLY;BYDAY=-2147483648MO",
+            "FREQ=YEARLY;BYMONTH=13;BYMONTHDAY=40",
+            "FREQ=WEEKLY;BYDAY=",
+            "FREQ=SECONDLY;BYSECOND=99",
+            "20220106T100000Z/P9999999999W",
+            "20220106T100000Z/",
+            "=====",
+            "é",
+            "\\",
+            "-1",
+            "99999999999",
+            "+9999",
+            "-000000",
+            "Europe/Rome",
+        ];
+        for name in names {
+            for param in params {
+                for value in values {
+                    let line = format!("{name}{param}:{value}");
+                    for text in [
+                        format!(
+                            "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:1\r\n\
+                             DTSTAMP:20220101T000000Z\r\n{line}\r\nEND:VEVENT\r\nEND:VCALENDAR"
+                        ),
+                        format!(
+                            "BEGIN:VCALENDAR\r\nBEGIN:VTIMEZONE\r\nTZID:X\r\nBEGIN:STANDARD\r\n\
+                             {line}\r\nEND:STANDARD\r\nEND:VTIMEZONE\r\n{line}\r\nEND:VCALENDAR"
+                        ),
+                    ] {
+                        let _ = VCalendar::parse(&text);
+                        let _ = VCalendar::parse_with(&text, &ParseOptions::strict());
+                    }
+                }
+            }
+        }
+
+        // and a few random combinations of them
+        for _ in 0..1000 {
+            let mut lines = vec!["BEGIN:VCALENDAR".to_owned()];
+            for _ in 0..random(3) {
+                let component = ["VEVENT", "VTIMEZONE", "STANDARD", "VTODO"][random(4)];
+                lines.push(format!("BEGIN:{component}"));
+                for _ in 0..random(8) {
+                    lines.push(format!(
+                        "{}{}:{}",
+                        names[random(names.len())],
+                        params[random(params.len())],
+                        values[random(values.len())]
+                    ));
+                }
+                if random(4) > 0 {
+                    lines.push(format!("END:{component}"));
+                }
+            }
+            lines.push("END:VCALENDAR".to_owned());
+
+            let text = lines.join(["\r\n", "\n", "\r\n "][random(3)]);
+            let _ = VCalendar::parse(&text);
+        }
+
+        // deeply nested blocks
+        let nested = "BEGIN:X\r\n".repeat(100_000) + &"END:X\r\n".repeat(100_000);
+        assert!(VCalendar::parse(&nested).is_err());
+    }
+
+    #[test]
+    fn parse_all() {
+        let text = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VEVENT",
+            "UID:1",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "END:VEVENT",
+            "END:VCALENDAR",
+            "",
+            "BEGIN:VCALENDAR",
+            "BEGIN:VEVENT",
+            "UID:2",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "UID:3",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "END:VEVENT",
+            "END:VCALENDAR",
+            "",
+            // without END:VCALENDAR
+            "BEGIN:VCALENDAR",
+            "BEGIN:VEVENT",
+            "UID:4",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "END:VEVENT",
+        ]
+        .join("\r\n");
+
+        let calendars = VCalendar::parse_all(&text).unwrap();
+        assert_eq!(
+            calendars
+                .iter()
+                .map(|calendar| calendar
+                    .events
+                    .iter()
+                    .filter_map(|event| event.uid.as_deref())
+                    .collect::<Vec<_>>())
+                .collect::<Vec<_>>(),
+            [vec!["1"], vec!["2", "3"], vec!["4"]]
+        );
+
+        assert!(VCalendar::parse_all("").unwrap().is_empty());
+
+        // component names are case insensitive
+        let lowercase = [
+            "begin:vcalendar",
+            "begin:vevent",
+            "UID:1",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "end:vevent",
+            "end:vcalendar",
+            "Begin:VCalendar",
+            "END:VCALENDAR",
+        ]
+        .join("\r\n");
+        let calendars = VCalendar::parse_all(&lowercase).unwrap();
+        assert_eq!(calendars.len(), 2);
+        assert_eq!(calendars[0].events[0].uid.as_deref(), Some("1"));
+        let invalid = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VEVENT",
+            "UID:1",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:invalid",
+            "END:VEVENT",
+            "END:VCALENDAR",
+            "BEGIN:VCALENDAR",
+            "BEGIN:VEVENT",
+            "UID:2",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ]
+        .join("\r\n");
+        assert!(VCalendar::parse_all(&invalid).is_err());
+    }
+
+    #[test]
+    fn summary() {
+        let text = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VTODO",
+            "UID:todo",
+            "END:VTODO",
+            "BEGIN:VEVENT",
+            "UID:1",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20211231T100000Z",
+            "RRULE:FREQ=DAILY",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "UID:2",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART;VALUE=DATE:20230101",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "UID:3",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ]
+        .join("\r\n");
+        let calendar = VCalendar::parse(&text).unwrap();
+
+        assert_eq!(
+            calendar.summary(),
+            CalendarSummary {
+                events: 3,
+                recurring_events: 1,
+                todos: 1,
+                journals: 0,
+                timezones: 0,
+                earliest_start: Some(DateOrDateTime::DateTime(
+                    Utc.with_ymd_and_hms(2021, 12, 31, 10, 0, 0).unwrap()
+                )),
+                latest_start: Some(DateOrDateTime::WholeDay(
+                    Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap()
+                )),
+            }
+        );
 
-        Ok(Self { timezones, events })
+        let empty = VCalendar::default().summary();
+        assert_eq!((empty.events, empty.earliest_start), (0, None));
     }
 }
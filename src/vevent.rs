@@ -1,12 +1,25 @@
 use crate::{
+    attachment::{Attachment, AttachmentParseError},
     block::Block,
-    date_or_date_time::{DateIntersectError, DateOrDateTime, EventOverlap},
-    rrule::{RRule, RRuleParseError},
-    vevent_iterator::VEventIterator,
-    TzIdDateTime,
+    date_or_date_time::{
+        DateIntersectError, DateOrDateTime, DateOrDateTimeParseError, EventOverlap,
+    },
+    duration::{format_duration, parse_duration, DurationParseError},
+    ical_line_parser::{find_unquoted, param_value, split_unquoted, split_value},
+    parse_options::ParseOptions,
+    rrule::{Options, RRule, RRuleParseError},
+    text::{escape_text, unescape_text},
+    tzid_date_time::{tzid_param, TzIdDateTimeFormatError},
+    vevent_iterator::{BoundedOccurrences, VEventIterator},
+    RDate, TzIdDateTime,
+};
+use chrono::{DateTime, Datelike, NaiveDateTime, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use std::{
+    hash::{Hash, Hasher},
+    num::ParseIntError,
+    ops::Range,
 };
-use chrono::{DateTime, Datelike, FixedOffset, Local, NaiveDateTime, TimeZone, Utc};
-use std::{num::ParseIntError, ops::Range};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -19,12 +32,34 @@ pub enum VEventFormatError {
     MissingMandatoryField { block: Block, field: String },
     #[error("Error parsing SEQUENCE number {block:?}. Error: {error}")]
     SequenceParseIntError { block: Block, error: ParseIntError },
+    #[error("Invalid PRIORITY {value:?}, must be 0-9 (block {block:?})")]
+    InvalidPriority { block: Block, value: String },
+    #[error("DTSTART {dt_start:?} plus DURATION {duration:?} is out of range")]
+    EndOutOfRange {
+        dt_start: DateOrDateTime,
+        duration: chrono::Duration,
+    },
+    #[error("DTEND and DURATION are mutually exclusive (block {block:?})")]
+    EndAndDuration { block: Block },
+    #[error("DTEND {dt_end:?} is before DTSTART {dt_start:?}")]
+    EndBeforeStart {
+        dt_start: DateOrDateTime,
+        dt_end: DateOrDateTime,
+    },
     #[error("RRule parse error")]
     RRuleParseError(#[from] RRuleParseError),
     #[error("TzIdDateTime parse error")]
-    TzIdDateTimeFormatError(#[from] crate::TzIdDateTimeFormatError),
+    TzIdDateTimeFormatError(#[from] TzIdDateTimeFormatError),
+    #[error("ATTACH parse error")]
+    AttachmentParseError(#[from] AttachmentParseError),
     #[error("Chrono parse error")]
     ChronoParseError(#[from] chrono::ParseError),
+    #[error("Date parse error")]
+    DateOrDateTimeParseError(#[from] DateOrDateTimeParseError),
+    #[error("DURATION parse error")]
+    DurationParseError(#[from] DurationParseError),
+    #[error("{property} must be a UTC date time, got {value:?}")]
+    NotUtc { property: String, value: String },
 }
 
 impl VEventFormatError {
@@ -43,34 +78,118 @@ impl VEventFormatError {
     pub fn sequence_parse_int_error(block: Block, error: ParseIntError) -> Self {
         VEventFormatError::SequenceParseIntError { block, error }
     }
+    pub fn invalid_priority(block: Block, value: impl Into<String>) -> Self {
+        VEventFormatError::InvalidPriority {
+            value: value.into(),
+            block,
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
+/// An event. Two events are equal if all their fields are, but they hash by
+/// `(uid, recurrence_id)` only, so that revisions of the same event land in the same
+/// `HashMap` bucket: volatile fields such as `dt_stamp` are intentionally ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct VEvent {
+    pub uid: Option<String>,
+    /// `RECURRENCE-ID`, set when the event overrides a single instance of a recurring one.
+    pub recurrence_id: Option<DateOrDateTime>,
     pub dt_created: DateOrDateTime,
     pub dt_last_modified: DateOrDateTime,
     pub dt_start: DateOrDateTime,
     pub dt_end: DateOrDateTime,
+    /// `DURATION`, when given instead of `DTEND`. `dt_end` is then `dt_start` plus it.
+    pub duration: Option<chrono::Duration>,
+    /// `TZID` parameter of `DTSTART`, if any. `dt_start` is converted to UTC regardless.
+    pub dt_start_tzid: Option<String>,
+    /// `TZID` parameter of `DTEND`, if any.
+    pub dt_end_tzid: Option<String>,
+    /// `TZID` parameter of `RECURRENCE-ID`, if any.
+    pub recurrence_id_tzid: Option<String>,
     pub dt_stamp: DateOrDateTime,
     pub summary: String,
+    /// `LANGUAGE` parameter of `SUMMARY`, e.g. `en-US`.
+    pub summary_language: Option<String>,
     pub description: Option<String>,
+    /// `LANGUAGE` parameter of `DESCRIPTION`.
+    pub description_language: Option<String>,
+    /// `ALTREP` parameter of `DESCRIPTION`: the URI of a richer version of it, often HTML.
+    pub description_altrep: Option<String>,
     pub rrule: Option<RRule>,
     pub exdates: Vec<TzIdDateTime>,
+    pub rdates: Vec<RDate>,
     pub sequence: u32,
+    /// `PRIORITY`, from 1 (highest) to 9 (lowest). 0 means undefined.
+    pub priority: Option<u8>,
     pub status: Option<String>,
+    /// `ORGANIZER` as written after the property name: the parameters, `:` and the calendar
+    /// address (e.g. `CN=John:mailto:john@example.com`), or just the calendar address without
+    /// parameters (e.g. `mailto:john@example.com`).
     pub organizer: Option<String>,
     pub google_conference_url: Option<String>,
+    pub color: Option<String>,
+    pub comments: Vec<String>,
+    pub attachments: Vec<Attachment>,
+    /// Names of the properties above in the order they first appear in the source, so that
+    /// serializing a parsed event reproduces its layout. Empty for events built in code,
+    /// which are serialized in a fixed order.
+    pub property_order: Vec<String>,
+}
+
+/// How many occurrences [`VEvent::occurrence_count`] and [`VEvent::bounded_occurrences`]
+/// generate at most.
+pub const MAX_GENERATED_OCCURRENCES: usize = 100_000;
+
+/// The properties mapped to [`VEvent`] fields, in the order they are serialized unless
+/// [`VEvent::property_order`] says otherwise.
+const PROPERTIES: &[&str] = &[
+    "UID",
+    "RECURRENCE-ID",
+    "CREATED",
+    "LAST-MODIFIED",
+    "DTSTAMP",
+    "DTSTART",
+    "DTEND",
+    "DURATION",
+    "SUMMARY",
+    "DESCRIPTION",
+    "SEQUENCE",
+    "PRIORITY",
+    "RRULE",
+    "EXDATE",
+    "RDATE",
+    "STATUS",
+    "ORGANIZER",
+    "X-GOOGLE-CONFERENCE",
+    "COLOR",
+    "COMMENT",
+    "ATTACH",
+];
+
+impl Hash for VEvent {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.uid.hash(state);
+        self.recurrence_id.hash(state);
+    }
+}
+
+/// Coarse grained `PRIORITY`, as suggested by RFC 5545 (3.8.1.9) for three-level
+/// applications.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PriorityLevel {
+    High,
+    Medium,
+    Low,
+    Undefined,
 }
 
 #[derive(Debug, Clone)]
 pub struct OccurrenceResult {
     pub occurrence: Range<DateOrDateTime>,
     pub event_overlap: EventOverlap,
-}
-
-fn midnight(d: DateTime<Utc>) -> DateTime<Utc> {
-    Utc.with_ymd_and_hms(d.year(), d.month(), d.day(), 0, 0, 0)
-        .unwrap()
+    /// Position of the occurrence, starting from 1 for `DTSTART`. As for `COUNT`, occurrences
+    /// removed by EXDATE are counted too, as in `VEventIterator::count_so_far`.
+    pub index: u32,
 }
 
 impl VEvent {
@@ -78,27 +197,173 @@ impl VEvent {
         self.dt_start
     }
 
+    /// Whether the event spans whole days (`DTSTART;VALUE=DATE`) instead of having a time.
+    pub fn is_all_day(&self) -> bool {
+        matches!(self.dt_start, DateOrDateTime::WholeDay(_))
+    }
+
+    /// Whether the event has been cancelled (`STATUS:CANCELLED`).
+    pub fn is_cancelled(&self) -> bool {
+        self.status
+            .as_deref()
+            .is_some_and(|status| status.trim().eq_ignore_ascii_case("CANCELLED"))
+    }
+
+    /// Whether the event repeats, i.e. it has a recurrence rule or recurrence dates.
+    pub fn is_recurring(&self) -> bool {
+        self.rrule.is_some() || !self.rdates.is_empty()
+    }
+
+    /// How many occurrences the event has, `None` if its recurrence rule has neither `COUNT`
+    /// nor `UNTIL`. As for [`OccurrenceResult::index`], occurrences removed by EXDATE are
+    /// counted too, so that the last occurrence is "n of n".
+    ///
+    /// With `COUNT` alone, or an `UNTIL` rule with a fixed step (daily, weekly, monthly on a
+    /// day every month has, yearly) and no EXDATEs or RDATEs, the count is computed without
+    /// generating the occurrences. Otherwise they are generated, and `None` is returned too
+    /// if there are more than [`MAX_GENERATED_OCCURRENCES`].
+    pub fn occurrence_count(&self) -> Option<usize> {
+        let count = self
+            .rrule
+            .as_ref()
+            .and_then(|rrule| rrule.common_options().count)
+            .map(|count| count as usize);
+
+        match &self.rrule {
+            // only the first occurrence of these is generated, COUNT is all we know
+            Some(RRule::Generic(_)) => count,
+            // RDATEs add to COUNT, without COUNT the rule never ends anyway
+            Some(rrule)
+                if rrule.common_options().until.is_none()
+                    && (count.is_none() || self.rdates.is_empty()) =>
+            {
+                count
+            }
+            // UNTIL (possibly along with COUNT) or RDATEs
+            _ => {
+                let mut iter = self.into_iter();
+                if let Some(until_count) = iter.count_until() {
+                    return Some(count.map_or(until_count, |count| count.min(until_count)));
+                }
+
+                iter.by_ref().take(MAX_GENERATED_OCCURRENCES).for_each(drop);
+                iter.next().is_none().then(|| iter.count_so_far() as usize)
+            }
+        }
+    }
+
+    /// The occurrences of the event, in an iterator that can also go backward from the last
+    /// one, e.g. to find the most recent past occurrence. The occurrences are generated
+    /// upfront, so `None` is returned for unbounded rules and for rules with more than
+    /// [`MAX_GENERATED_OCCURRENCES`] occurrences, see [`VEvent::occurrence_count`].
+    pub fn bounded_occurrences(&self) -> Option<BoundedOccurrences> {
+        let count = self.occurrence_count()?;
+        (count <= MAX_GENERATED_OCCURRENCES).then(|| BoundedOccurrences::new(self))
+    }
+
+    /// Timezones referenced by `TZID` in `DTSTART`, `DTEND`, `RECURRENCE-ID`, `EXDATE` and
+    /// `RDATE` as written, possibly repeated. Unknown ones, whose values were read as floating
+    /// times, are included.
+    pub fn referenced_tzids(&self) -> impl Iterator<Item = &str> {
+        self.dt_start_tzid
+            .iter()
+            .chain(self.dt_end_tzid.iter())
+            .chain(self.recurrence_id_tzid.iter())
+            .map(String::as_str)
+            .chain(
+                self.exdates
+                    .iter()
+                    .filter_map(|exdate| exdate.tzid.as_deref()),
+            )
+            .chain(self.rdates.iter().filter_map(|rdate| match rdate {
+                RDate::DateTime(date_time) => date_time.tzid.as_deref(),
+                RDate::Period { start, .. } => start.tzid.as_deref(),
+            }))
+    }
+
+    /// [`PriorityLevel`] of the event: 1-4 are high, 5 medium and 6-9 low.
+    pub fn priority_level(&self) -> PriorityLevel {
+        match self.priority {
+            Some(1..=4) => PriorityLevel::High,
+            Some(5) => PriorityLevel::Medium,
+            Some(6..=9) => PriorityLevel::Low,
+            _ => PriorityLevel::Undefined,
+        }
+    }
+
+    /// Hash of the content of the event, to detect whether it really changed between two
+    /// downloads. `DTSTAMP` is ignored since it changes on every export, and so is the order
+    /// of the properties. The value only depends on the serialized properties (see
+    /// `Block::from`), hashed with FNV-1a, so it can be persisted.
+    pub fn content_hash(&self) -> u64 {
+        let mut lines = Block::from(self).inner_lines;
+        lines.retain(|line| {
+            !line[..line.find([';', ':']).unwrap_or(line.len())].eq_ignore_ascii_case("DTSTAMP")
+        });
+        lines.sort();
+
+        fnv1a(lines.join("\r\n").as_bytes())
+    }
+
+    /// Base period of the event, `DTSTART..DTEND`, ignoring any recurrence rule.
+    pub fn period(&self) -> Range<DateOrDateTime> {
+        self.dt_start..self.dt_end
+    }
+
+    /// Base period of the event in the time zone `tz`, see [`DateOrDateTime::with_timezone`].
+    pub fn local_range(&self, tz: &chrono_tz::Tz) -> Range<DateTime<chrono_tz::Tz>> {
+        self.dt_start.with_timezone(tz)..self.dt_end.with_timezone(tz)
+    }
+
+    /// Length of each occurrence. Whole day events have an exclusive `DTEND`, so
+    /// 20220101..20220104 lasts three days; a whole day event without `DTEND` lasts one day.
+    pub fn duration(&self) -> chrono::Duration {
+        if let Some(duration) = self.duration {
+            return duration;
+        }
+
+        match (self.dt_start, self.dt_end) {
+            (DateOrDateTime::WholeDay(start), DateOrDateTime::WholeDay(end)) if start == end => {
+                chrono::Duration::days(1)
+            }
+            (dt_start, dt_end) => dt_end - dt_start,
+        }
+    }
+
+    /// First occurrence, among the first `max` ones, for which `pred` returns `true`.
+    pub fn find_occurrence<F: Fn(&Range<DateOrDateTime>) -> bool>(
+        &self,
+        pred: F,
+        max: usize,
+    ) -> Option<Range<DateOrDateTime>> {
+        self.into_iter()
+            .take(max)
+            .find(|occurrence| pred(occurrence))
+    }
+
     pub fn next_occurrence_since(
         &self,
         dt: DateOrDateTime,
     ) -> Result<Option<OccurrenceResult>, DateIntersectError> {
         //println!("called next_occurrence_since({self:?}, {dt:?})");
+        Self::next_occurrence_in(self.into_iter(), dt)
+    }
 
-        for occurrence in self.into_iter() {
-            let event_overlap = {
-                // handle the special case of start and end dates being WholeDay. We consider the
-                // final date the last second of the previous end date.
-                if let (DateOrDateTime::WholeDay(wd_start), DateOrDateTime::WholeDay(wd_end)) =
-                    (occurrence.start, occurrence.end)
-                {
-                    dt.intersects(
-                        DateOrDateTime::DateTime(midnight(wd_start)),
-                        DateOrDateTime::DateTime(midnight(wd_end) - chrono::Duration::seconds(1)),
-                    )?
-                } else {
-                    dt.intersects(occurrence.start, occurrence.end)?
-                }
-            };
+    /// Same as [`VEvent::next_occurrence_since`], but `None` if the event is cancelled.
+    pub fn next_occurrence_since_skipping_cancelled(
+        &self,
+        dt: DateOrDateTime,
+    ) -> Result<Option<OccurrenceResult>, DateIntersectError> {
+        Self::next_occurrence_in(self.into_iter().skip_cancelled(true), dt)
+    }
+
+    fn next_occurrence_in(
+        mut occurrences: VEventIterator<'_>,
+        dt: DateOrDateTime,
+    ) -> Result<Option<OccurrenceResult>, DateIntersectError> {
+        occurrences.fast_forward(dt);
+        while let Some(occurrence) = occurrences.next() {
+            let event_overlap = dt.intersects_day_aware(occurrence.start, occurrence.end)?;
 
             log::debug!("event_overlap == {:?} ==> {:?}", occurrence, event_overlap);
 
@@ -108,6 +373,7 @@ impl VEvent {
                     return Ok(Some(OccurrenceResult {
                         occurrence,
                         event_overlap,
+                        index: occurrences.count_so_far(),
                     }));
                 }
             }
@@ -116,78 +382,175 @@ impl VEvent {
 
         Ok(None)
     }
+
+    /// Occurrences that have not ended before `from`, i.e. ending after it or starting at or
+    /// after it. For rules with a fixed step (secondly without BYSECOND, daily, weekly,
+    /// monthly on a day up to the 28th and yearly unless starting on February 29th) and
+    /// without EXDATE or RDATE the earlier occurrences are skipped arithmetically instead of
+    /// being generated one by one. Chain `until` to bound the window.
+    pub fn iter_from(&self, from: DateOrDateTime) -> VEventIterator<'_> {
+        self.into_iter().starting_from(from)
+    }
+
+    /// Streaming version of [`VEvent::next_occurrence_since`]: every occurrence that has not
+    /// finished before `reference`, along with how it overlaps the day of `reference`. The
+    /// iterator is unbounded if the recurrence rule is, and stops at the first occurrence
+    /// ending before it starts.
+    pub fn occurrences_with_overlap(
+        &self,
+        reference: DateOrDateTime,
+    ) -> impl Iterator<Item = OccurrenceResult> + '_ {
+        let mut occurrences = self.into_iter();
+
+        std::iter::from_fn(move || {
+            let occurrence = occurrences.next()?;
+            reference
+                .intersects_day_aware(occurrence.start, occurrence.end)
+                .ok()
+                .map(|event_overlap| OccurrenceResult {
+                    occurrence,
+                    event_overlap,
+                    index: occurrences.count_so_far(),
+                })
+        })
+        .filter(|result| result.event_overlap != EventOverlap::FinishesPast)
+    }
 }
 
 impl TryFrom<Block> for VEvent {
     type Error = VEventFormatError;
 
     fn try_from(block: Block) -> Result<Self, Self::Error> {
+        Self::from_block(block, &ParseOptions::default())
+    }
+}
+
+impl VEvent {
+    pub(crate) fn from_block(
+        block: Block,
+        options: &ParseOptions,
+    ) -> Result<Self, VEventFormatError> {
+        let mut uid = None;
+        let mut recurrence_id = None;
         let mut dt_created = None;
         let mut dt_last_modified = None;
         let mut dt_start: Option<DateOrDateTime> = None;
         let mut dt_end = None;
+        let mut duration = None;
+        let mut dt_start_tzid = None;
+        let mut dt_end_tzid = None;
+        let mut recurrence_id_tzid = None;
         let mut dt_stamp = None;
         let mut summary = None;
+        let mut summary_language = None;
         let mut description = None;
+        let mut description_language = None;
+        let mut description_altrep = None;
         let mut rrule = None;
         let mut exdates = Vec::new();
+        let mut rdates = Vec::new();
         let mut sequence = None;
+        let mut priority = None;
         let mut status = None;
         let mut organizer = None;
         let mut google_conference_url = None;
+        let mut color = None;
+        let mut comments = Vec::new();
+        let mut attachments = Vec::new();
+        let mut property_order: Vec<String> = Vec::new();
 
-        for line in block.inner_lines.iter() {
-            let idx_colon = line.find(':').unwrap_or(line.len());
-            let tag = &line[0..idx_colon];
-            let extra = if idx_colon + 1 < line.len() {
-                Some(&line[idx_colon + 1..])
+        let text = |value: &str| {
+            if options.trim_values {
+                value.trim().to_owned()
             } else {
-                None
-            };
+                value.to_owned()
+            }
+        };
 
-            match tag {
+        for line in block.inner_lines.iter() {
+            let name = line[..line.find([';', ':']).unwrap_or(line.len())].to_ascii_uppercase();
+            if PROPERTIES.contains(&name.as_str()) && !property_order.contains(&name) {
+                property_order.push(name);
+            }
+
+            let idx_colon = find_unquoted(line, &[':']).unwrap_or(line.len());
+            // property names are case insensitive
+            let tag = line[0..idx_colon].to_ascii_uppercase();
+            // `NAME:` is an empty value, not a missing one
+            let extra = line.get(idx_colon + 1..);
+
+            match tag.as_str() {
                 "LAST-MODIFIED" => {
-                    dt_last_modified =
+                    dt_last_modified = Some(string_to_utc_datetime(
+                        "LAST-MODIFIED",
+                        extra.ok_or_else(|| VEventFormatError::missing_colon(block.clone()))?,
+                        options,
+                    )?);
+                }
+                "DTSTART" => {
+                    dt_start =
                         Some(string_to_date_or_datetime(extra.ok_or_else(|| {
                             VEventFormatError::missing_colon(block.clone())
                         })?)?);
                 }
-                "DTSTART" => {
-                    dt_start = Some(DateOrDateTime::DateTime(string_to_datetime(
-                        extra.ok_or_else(|| VEventFormatError::missing_colon(block.clone()))?,
-                    )?));
-                }
                 "DTEND" => {
                     dt_end =
                         Some(string_to_date_or_datetime(extra.ok_or_else(|| {
                             VEventFormatError::missing_colon(block.clone())
                         })?)?);
                 }
-                "CREATED" => {
-                    dt_created =
-                        Some(string_to_date_or_datetime(extra.ok_or_else(|| {
+                "DURATION" => {
+                    duration =
+                        Some(parse_duration(extra.ok_or_else(|| {
                             VEventFormatError::missing_colon(block.clone())
                         })?)?);
                 }
+                "CREATED" => {
+                    dt_created = Some(string_to_utc_datetime(
+                        "CREATED",
+                        extra.ok_or_else(|| VEventFormatError::missing_colon(block.clone()))?,
+                        options,
+                    )?);
+                }
                 "DTSTAMP" => {
-                    dt_stamp =
+                    dt_stamp = Some(string_to_utc_datetime(
+                        "DTSTAMP",
+                        extra.ok_or_else(|| VEventFormatError::missing_colon(block.clone()))?,
+                        options,
+                    )?);
+                }
+                "UID" => uid = extra.map(|e| e.to_string()),
+                "RECURRENCE-ID" => {
+                    recurrence_id =
                         Some(string_to_date_or_datetime(extra.ok_or_else(|| {
                             VEventFormatError::missing_colon(block.clone())
                         })?)?);
                 }
                 "SUMMARY" => {
-                    summary = Some(
-                        extra
-                            .ok_or_else(|| VEventFormatError::missing_colon(block.clone()))?
-                            .to_string(),
-                    );
+                    summary =
+                        Some(text(extra.ok_or_else(|| {
+                            VEventFormatError::missing_colon(block.clone())
+                        })?));
                 }
-                "DESCRIPTION" => description = extra.map(|e| e.to_string()),
+                "DESCRIPTION" => description = extra.map(text),
                 "SEQUENCE" => {
                     sequence = extra.map(|e| e.parse::<u32>()).transpose().map_err(|e| {
                         VEventFormatError::sequence_parse_int_error(block.clone(), e)
                     })?;
                 }
+                "PRIORITY" => {
+                    priority = extra
+                        .map(|e| {
+                            e.trim()
+                                .parse::<u8>()
+                                .ok()
+                                .filter(|p| *p <= 9)
+                                .ok_or_else(|| {
+                                    VEventFormatError::invalid_priority(block.clone(), e)
+                                })
+                        })
+                        .transpose()?;
+                }
                 "RRULE" => {
                     rrule = Some(
                         extra
@@ -205,18 +568,55 @@ impl TryFrom<Block> for VEvent {
                 "X-GOOGLE-CONFERENCE" => {
                     google_conference_url = extra.map(|e| e.to_string());
                 }
+                "EXDATE" => {
+                    // without parameters: UTC or floating time
+                    exdates.extend(TzIdDateTime::parse_list(
+                        extra.ok_or_else(|| VEventFormatError::missing_colon(block.clone()))?,
+                    )?);
+                }
+                "RDATE" => {
+                    rdates.extend(RDate::parse_list(
+                        extra.ok_or_else(|| VEventFormatError::missing_colon(block.clone()))?,
+                    )?);
+                }
+                "COLOR" => color = extra.map(|e| e.to_string()),
+                "ORGANIZER" => organizer = extra.map(|e| e.to_string()),
+                "COMMENT" => {
+                    comments.push(extra.map(|e| unescape_text(&text(e))).unwrap_or_default())
+                }
+                "ATTACH" => {
+                    attachments.push(Attachment::parse(
+                        None,
+                        extra.ok_or_else(|| VEventFormatError::missing_colon(block.clone()))?,
+                    )?);
+                }
                 _ => {} // ignore
             }
 
             let idx_semicolon = line.find(';').unwrap_or(line.len());
-            let tag = &line[0..idx_semicolon];
+            let tag = line[0..idx_semicolon].to_ascii_uppercase();
             let extra = if idx_semicolon + 1 < line.len() {
                 Some(&line[idx_semicolon + 1..])
             } else {
                 None
             };
 
-            match tag {
+            match tag.as_str() {
+                "SUMMARY" => {
+                    let extra =
+                        extra.ok_or_else(|| VEventFormatError::missing_colon(block.clone()))?;
+                    let (_, value) = split_value(extra)
+                        .ok_or_else(|| VEventFormatError::missing_colon(block.clone()))?;
+                    summary = Some(text(value));
+                    summary_language = param_value(extra, "LANGUAGE");
+                }
+                "DESCRIPTION" => {
+                    if let Some(extra) = extra {
+                        description = split_value(extra).map(|(_, value)| text(value));
+                        description_language = param_value(extra, "LANGUAGE");
+                        description_altrep = param_value(extra, "ALTREP");
+                    }
+                }
                 "ORGANIZER" => {
                     organizer = Some(
                         extra
@@ -224,24 +624,68 @@ impl TryFrom<Block> for VEvent {
                             .to_string(),
                     );
                 }
+                "ATTACH" => {
+                    let (params, value) = extra
+                        .and_then(split_value)
+                        .ok_or_else(|| VEventFormatError::missing_colon(block.clone()))?;
+                    attachments.push(Attachment::parse(Some(params), value)?);
+                }
+                "COMMENT" => {
+                    let (_, value) = extra
+                        .and_then(split_value)
+                        .ok_or_else(|| VEventFormatError::missing_colon(block.clone()))?;
+                    comments.push(unescape_text(&text(value)));
+                }
                 "EXDATE" => {
                     let extra =
                         extra.ok_or_else(|| VEventFormatError::missing_semicolon(block.clone()))?;
                     log::trace!("parsing EXDATE ==> {}", extra);
-                    exdates.push(TzIdDateTime::try_from(extra)?);
+                    exdates.extend(parse_date_list(
+                        extra,
+                        options,
+                        TzIdDateTime::parse_list,
+                        |exdate, tzid| exdate.tzid = Some(tzid.to_owned()),
+                    )?);
+                }
+                "RDATE" => {
+                    let extra =
+                        extra.ok_or_else(|| VEventFormatError::missing_semicolon(block.clone()))?;
+                    rdates.extend(parse_date_list(
+                        extra,
+                        options,
+                        RDate::parse_list,
+                        |rdate, tzid| match rdate {
+                            RDate::DateTime(date_time) => date_time.tzid = Some(tzid.to_owned()),
+                            RDate::Period { start, end } => {
+                                start.tzid = Some(tzid.to_owned());
+                                end.tzid = Some(tzid.to_owned());
+                            }
+                        },
+                    )?);
                 }
                 "DTSTART" => {
+                    dt_start_tzid = extra.and_then(tzid_param);
                     dt_start = Some(
                         extra
-                            .map(to_tziddate_or_date)
+                            .map(|extra| to_tziddate_or_date(extra, options))
                             .transpose()?
                             .ok_or_else(|| VEventFormatError::missing_semicolon(block.clone()))?,
                     );
                 }
                 "DTEND" => {
+                    dt_end_tzid = extra.and_then(tzid_param);
                     dt_end = Some(
                         extra
-                            .map(to_tziddate_or_date)
+                            .map(|extra| to_tziddate_or_date(extra, options))
+                            .transpose()?
+                            .ok_or_else(|| VEventFormatError::missing_semicolon(block.clone()))?,
+                    );
+                }
+                "RECURRENCE-ID" => {
+                    recurrence_id_tzid = extra.and_then(tzid_param);
+                    recurrence_id = Some(
+                        extra
+                            .map(|extra| to_tziddate_or_date(extra, options))
                             .transpose()?
                             .ok_or_else(|| VEventFormatError::missing_semicolon(block.clone()))?,
                     );
@@ -253,34 +697,214 @@ impl TryFrom<Block> for VEvent {
         let dt_start = dt_start
             .ok_or_else(|| VEventFormatError::missing_mandatory_field(block.clone(), "DTSTART"))?;
 
+        if dt_end.is_some() && duration.is_some() {
+            if options.strict {
+                return Err(VEventFormatError::EndAndDuration { block });
+            }
+            log::warn!("both DTEND and DURATION given, ignoring DURATION");
+            duration = None;
+        }
+
+        // if there is no DT_END tag, the event lasts DURATION or, if missing, ends when it
+        // starts.
+        let dt_end = match (dt_end, duration) {
+            (Some(dt_end), _) => dt_end,
+            (None, Some(duration)) => dt_start
+                .checked_add_signed(duration)
+                .ok_or(VEventFormatError::EndOutOfRange { dt_start, duration })?,
+            (None, None) => dt_start,
+        };
+        if dt_end < dt_start {
+            return Err(VEventFormatError::EndBeforeStart { dt_start, dt_end });
+        }
+
+        if options.strict && uid.is_none() {
+            return Err(VEventFormatError::missing_mandatory_field(
+                block.clone(),
+                "UID",
+            ));
+        }
+        let dt_stamp = match dt_stamp {
+            Some(dt_stamp) => dt_stamp,
+            None if options.strict => {
+                return Err(VEventFormatError::missing_mandatory_field(
+                    block.clone(),
+                    "DTSTAMP",
+                ))
+            }
+            None => dt_start,
+        };
+
         Ok(VEvent {
-            dt_last_modified: dt_last_modified.ok_or_else(|| {
-                VEventFormatError::missing_mandatory_field(block.clone(), "LAST-MODIFIED")
-            })?,
+            uid,
+            recurrence_id,
+            // CREATED and LAST-MODIFIED are optional (RFC 5545, 3.6.1)
+            dt_last_modified: dt_last_modified.unwrap_or(dt_stamp),
             dt_start,
-            dt_end: dt_end.unwrap_or(dt_start), // if there is no DT_END tag, it means end is the same as start.
-            dt_created: dt_created.ok_or_else(|| {
-                VEventFormatError::missing_mandatory_field(block.clone(), "CREATED")
-            })?,
-            dt_stamp: dt_stamp.ok_or_else(|| {
-                VEventFormatError::missing_mandatory_field(block.clone(), "DTSTAMP")
-            })?,
-            summary: summary.ok_or_else(|| {
-                VEventFormatError::missing_mandatory_field(block.clone(), "SUMMARY")
-            })?,
+            dt_end,
+            duration,
+            dt_start_tzid,
+            dt_end_tzid,
+            recurrence_id_tzid,
+            dt_created: dt_created.unwrap_or(dt_stamp),
+            dt_stamp,
+            summary: summary.unwrap_or_default(),
+            summary_language,
             description,
+            description_language,
+            description_altrep,
             rrule,
             exdates,
-            sequence: sequence.ok_or_else(|| {
-                VEventFormatError::missing_mandatory_field(block.clone(), "SEQUENCE")
-            })?,
+            rdates,
+            // RFC 5545 (3.8.7.4): SEQUENCE defaults to 0
+            sequence: sequence.unwrap_or(0),
+            priority,
             status,
             organizer,
             google_conference_url,
+            color,
+            comments,
+            attachments,
+            property_order,
         })
     }
 }
 
+impl From<&VEvent> for Block {
+    fn from(event: &VEvent) -> Self {
+        let date_property = |name: &str, date: DateOrDateTime| match date {
+            DateOrDateTime::WholeDay(_) => format!("{name};VALUE=DATE:{date}"),
+            DateOrDateTime::DateTime(_) | DateOrDateTime::Floating(_) => {
+                format!("{name}:{date}")
+            }
+        };
+
+        // a TZID is kept, with the time written in that zone
+        let zoned_property = |name: &str, date: DateOrDateTime, tzid: &Option<String>| {
+            let Some(tzid) = tzid else {
+                return date_property(name, date);
+            };
+            let local = match (date, tzid.parse::<Tz>()) {
+                (DateOrDateTime::DateTime(dt), Ok(tz)) => {
+                    dt.with_timezone(&tz).format("%Y%m%dT%H%M%S").to_string()
+                }
+                // an unknown zone was read as floating time
+                (DateOrDateTime::Floating(_), _) => date.to_string(),
+                _ => return date_property(name, date),
+            };
+            if tzid.contains([':', ';', ',']) {
+                format!("{name};TZID=\"{tzid}\":{local}")
+            } else {
+                format!("{name};TZID={tzid}:{local}")
+            }
+        };
+
+        let mut inner_lines = Vec::new();
+
+        if let Some(uid) = &event.uid {
+            inner_lines.push(format!("UID:{uid}"));
+        }
+        if let Some(recurrence_id) = event.recurrence_id {
+            inner_lines.push(zoned_property(
+                "RECURRENCE-ID",
+                recurrence_id,
+                &event.recurrence_id_tzid,
+            ));
+        }
+        inner_lines.push(date_property("CREATED", event.dt_created));
+        inner_lines.push(date_property("LAST-MODIFIED", event.dt_last_modified));
+        inner_lines.push(date_property("DTSTAMP", event.dt_stamp));
+        inner_lines.push(zoned_property(
+            "DTSTART",
+            event.dt_start,
+            &event.dt_start_tzid,
+        ));
+        // DTEND and DURATION are mutually exclusive
+        match event.duration {
+            Some(duration) => inner_lines.push(format!("DURATION:{}", format_duration(duration))),
+            None => inner_lines.push(zoned_property("DTEND", event.dt_end, &event.dt_end_tzid)),
+        }
+        // SUMMARY and DESCRIPTION are kept escaped
+        let language = |language: &Option<String>| match language {
+            Some(language) => format!(";LANGUAGE={language}"),
+            None => String::new(),
+        };
+        inner_lines.push(format!(
+            "SUMMARY{}:{}",
+            language(&event.summary_language),
+            event.summary
+        ));
+        if let Some(description) = &event.description {
+            // ALTREP is a URI, which must be quoted
+            let altrep = match &event.description_altrep {
+                Some(altrep) => format!(";ALTREP=\"{altrep}\""),
+                None => String::new(),
+            };
+            inner_lines.push(format!(
+                "DESCRIPTION{altrep}{}:{description}",
+                language(&event.description_language)
+            ));
+        }
+        inner_lines.push(format!("SEQUENCE:{}", event.sequence));
+        if let Some(priority) = event.priority {
+            inner_lines.push(format!("PRIORITY:{priority}"));
+        }
+        if let Some(rrule) = &event.rrule {
+            inner_lines.push(format!("RRULE:{}", rrule.to_rrule_string()));
+        }
+        for exdate in &event.exdates {
+            inner_lines.push(format!("EXDATE{}", exdate.to_ical_string()));
+        }
+        for rdate in &event.rdates {
+            inner_lines.push(format!("RDATE{}", rdate.to_ical_string()));
+        }
+        if let Some(status) = &event.status {
+            inner_lines.push(format!("STATUS:{status}"));
+        }
+        if let Some(organizer) = &event.organizer {
+            // parameters are `NAME=value`, a calendar address has no `=` before its scheme
+            let has_parameters =
+                organizer[..find_unquoted(organizer, &[':']).unwrap_or(0)].contains('=');
+            inner_lines.push(if has_parameters {
+                format!("ORGANIZER;{organizer}")
+            } else {
+                format!("ORGANIZER:{organizer}")
+            });
+        }
+        if let Some(google_conference_url) = &event.google_conference_url {
+            inner_lines.push(format!("X-GOOGLE-CONFERENCE:{google_conference_url}"));
+        }
+        if let Some(color) = &event.color {
+            inner_lines.push(format!("COLOR:{color}"));
+        }
+        for comment in &event.comments {
+            inner_lines.push(format!("COMMENT:{}", escape_text(comment)));
+        }
+        for attachment in &event.attachments {
+            inner_lines.push(attachment.to_ical_line());
+        }
+
+        // properties missing from the original layout go last, in the fixed order
+        if !event.property_order.is_empty() {
+            let position = |line: &String| {
+                let name = &line[..line.find([';', ':']).unwrap_or(line.len())];
+                event
+                    .property_order
+                    .iter()
+                    .position(|property| property == name)
+                    .unwrap_or(usize::MAX)
+            };
+            inner_lines.sort_by_key(position);
+        }
+
+        Block {
+            name: "VEVENT".to_owned(),
+            inner_lines,
+            inner_blocks: Vec::new(),
+        }
+    }
+}
+
 impl<'a> IntoIterator for &'a VEvent {
     type Item = Range<DateOrDateTime>;
     type IntoIter = VEventIterator<'a>;
@@ -290,42 +914,1174 @@ impl<'a> IntoIterator for &'a VEvent {
     }
 }
 
-pub(crate) fn string_to_date_or_datetime(s: &str) -> Result<DateOrDateTime, chrono::ParseError> {
+/// 64 bit FNV-1a, a fixed algorithm unlike [`std::collections::hash_map::DefaultHasher`].
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+pub(crate) fn string_to_date_or_datetime(
+    s: &str,
+) -> Result<DateOrDateTime, DateOrDateTimeParseError> {
+    let invalid_date_time = |_| DateOrDateTimeParseError::InvalidDateTime {
+        value: s.to_owned(),
+    };
+
     Ok(if s.len() == 8 {
-        let date = string_to_date(s)?;
+        let date = string_to_date(s).map_err(|_| DateOrDateTimeParseError::InvalidDate {
+            value: s.to_owned(),
+        })?;
         DateOrDateTime::WholeDay(
             Utc.with_ymd_and_hms(date.year(), date.month(), date.day(), 0, 0, 0)
                 .unwrap(),
         )
+    } else if s.ends_with('Z') {
+        DateOrDateTime::DateTime(string_to_datetime(s).map_err(invalid_date_time)?)
     } else {
-        DateOrDateTime::DateTime(string_to_datetime(s)?)
+        DateOrDateTime::Floating(string_to_naive_datetime(s).map_err(invalid_date_time)?)
     })
 }
 
+/// Parses a `DATE-TIME` without its trailing `Z`. The (non standard) fractional seconds some
+/// exporters add are accepted and discarded, iCalendar has no sub-second precision.
+pub(crate) fn string_to_naive_datetime(s: &str) -> Result<NaiveDateTime, chrono::ParseError> {
+    let date_time = NaiveDateTime::parse_from_str(s, "%Y%m%dT%H%M%S%.f")?;
+    Ok(date_time.with_nanosecond(0).unwrap_or(date_time))
+}
+
 fn string_to_datetime(s: &str) -> Result<DateTime<Utc>, chrono::ParseError> {
-    Ok(if s.ends_with('Z') {
-        DateTime::<FixedOffset>::parse_from_str(s, "%Y%m%dT%H%M%S%#z")?.with_timezone(&Utc)
-    } else {
-        let a = NaiveDateTime::parse_from_str(s, "%Y%m%dT%H%M%S")?;
-        let tz_offset = Local::now().offset().to_owned();
-        tz_offset
-            .from_local_datetime(&a)
-            .unwrap()
-            .with_timezone(&Utc)
-        //Utc.from_utc_datetime(&a)
-    })
+    // never the host local time, the same text must give the same instant on every machine
+    let s = s.strip_suffix('Z').unwrap_or(s);
+    Ok(Utc.from_utc_datetime(&string_to_naive_datetime(s)?))
+}
+
+/// Parses `DTSTAMP`, `CREATED` and `LAST-MODIFIED`, which must be UTC date times (RFC 5545,
+/// 3.8.7). Dates and date times without the trailing `Z` are an error in strict mode and
+/// are read as UTC otherwise.
+fn string_to_utc_datetime(
+    property: &str,
+    s: &str,
+    options: &ParseOptions,
+) -> Result<DateOrDateTime, VEventFormatError> {
+    match string_to_date_or_datetime(s)? {
+        date_time @ DateOrDateTime::DateTime(_) => Ok(date_time),
+        _ if options.strict => Err(VEventFormatError::NotUtc {
+            property: property.to_owned(),
+            value: s.to_owned(),
+        }),
+        date_or_floating => Ok(DateOrDateTime::DateTime(date_or_floating.date())),
+    }
 }
 
 fn string_to_date(s: &str) -> Result<DateTime<Utc>, chrono::ParseError> {
-    Ok(DateTime::<Local>::from_utc(
-        NaiveDateTime::parse_from_str(&format!("{s}T000000"), "%Y%m%dT%H%M%S")?,
-        Local::now().offset().to_owned(),
-    )
-    .with_timezone(&Utc))
+    // Whole days are anchored at UTC midnight regardless of the host timezone,
+    // so recurrence arithmetic on them never drifts by a day.
+    Ok(Utc.from_utc_datetime(&NaiveDateTime::parse_from_str(
+        &format!("{s}T000000"),
+        "%Y%m%dT%H%M%S",
+    )?))
 }
 
 fn to_tziddate_or_date(
     s: &str,
-) -> Result<DateOrDateTime, crate::tzid_date_time::TzIdDateTimeFormatError> {
-    Ok(s.parse::<TzIdDateTime>()?.date_time)
+    options: &ParseOptions,
+) -> Result<DateOrDateTime, TzIdDateTimeFormatError> {
+    match s.parse::<TzIdDateTime>() {
+        Ok(date_time) => Ok(date_time.date_time),
+        // the zone may still be defined by a VTIMEZONE, see `VCalendar::validate_timezones`
+        Err(TzIdDateTimeFormatError::UnknownTimeZone { tzid }) if !options.strict => {
+            log::warn!("unknown TZID {tzid:?}, reading {s:?} as floating time");
+            let (_, value) = split_value(s).unwrap_or(("", s));
+            Ok(string_to_date_or_datetime(value.trim())?)
+        }
+        Err(error) => Err(error),
+    }
+}
+
+/// Parses the values of `EXDATE` or `RDATE` with `parse_list`. Like
+/// [`to_tziddate_or_date`], an unknown TZID reads them as floating times when lenient; it's
+/// then put back on each value with `set_tzid`, so it can still be reported.
+fn parse_date_list<T>(
+    s: &str,
+    options: &ParseOptions,
+    parse_list: fn(&str) -> Result<Vec<T>, TzIdDateTimeFormatError>,
+    set_tzid: fn(&mut T, &str),
+) -> Result<Vec<T>, TzIdDateTimeFormatError> {
+    match parse_list(s) {
+        Err(TzIdDateTimeFormatError::UnknownTimeZone { tzid }) if !options.strict => {
+            log::warn!("unknown TZID {tzid:?}, reading {s:?} as floating times");
+            let (params, values) = split_value(s).unwrap_or(("", s));
+            let params = split_unquoted(params, ';')
+                .into_iter()
+                .filter(|param| {
+                    !param
+                        .trim()
+                        .split_once('=')
+                        .is_some_and(|(name, _)| name.eq_ignore_ascii_case("TZID"))
+                })
+                .collect::<Vec<_>>()
+                .join(";");
+            let mut list = parse_list(&format!("{params}:{values}"))?;
+            list.iter_mut().for_each(|value| set_tzid(value, &tzid));
+            Ok(list)
+        }
+        result => result,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn parse_event(lines: &[&str]) -> VEvent {
+        let mut all_lines = vec![
+            "BEGIN:VEVENT".to_owned(),
+            "CREATED:20220101T000000Z".to_owned(),
+            "LAST-MODIFIED:20220101T000000Z".to_owned(),
+            "DTSTAMP:20220101T000000Z".to_owned(),
+            "SEQUENCE:0".to_owned(),
+            "SUMMARY:Test".to_owned(),
+        ];
+        all_lines.extend(lines.iter().map(|l| l.to_string()));
+        all_lines.push("END:VEVENT".to_owned());
+
+        let block: Block = all_lines.as_slice().try_into().unwrap();
+        block.try_into().unwrap()
+    }
+
+    #[test]
+    fn dtstart_value_date() {
+        let event = parse_event(&[
+            "DTSTART;VALUE=DATE:20220101",
+            "DTEND;VALUE=DATE:20220102",
+            "RRULE:FREQ=YEARLY",
+        ]);
+
+        assert_eq!(
+            event.dt_start,
+            DateOrDateTime::WholeDay(Utc.with_ymd_and_hms(2022, 1, 1, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            event.dt_end,
+            DateOrDateTime::WholeDay(Utc.with_ymd_and_hms(2022, 1, 2, 0, 0, 0).unwrap())
+        );
+        assert!(event
+            .into_iter()
+            .take(3)
+            .all(|o| matches!(o.start, DateOrDateTime::WholeDay(_))));
+    }
+
+    #[test]
+    fn is_all_day() {
+        assert!(parse_event(&["DTSTART;VALUE=DATE:20220101"]).is_all_day());
+        assert!(!parse_event(&["DTSTART:20220106T100000Z"]).is_all_day());
+    }
+
+    #[test]
+    fn is_recurring() {
+        assert!(parse_event(&["DTSTART:20220106T100000Z", "RRULE:FREQ=DAILY"]).is_recurring());
+        assert!(!parse_event(&["DTSTART:20220106T100000Z"]).is_recurring());
+        assert!(
+            parse_event(&["DTSTART:20220106T100000Z", "RDATE:20220108T100000Z"]).is_recurring()
+        );
+    }
+
+    #[test]
+    fn content_hash() {
+        let event = parse_event(&["DTSTART:20220106T100000Z", "DTSTAMP:20220101T000000Z"]);
+        let restamped = parse_event(&["DTSTART:20220106T100000Z", "DTSTAMP:20220301T000000Z"]);
+        let renamed = parse_event(&["DTSTART:20220106T100000Z", "SUMMARY:Renamed"]);
+
+        assert_eq!(event.content_hash(), restamped.content_hash());
+        assert_ne!(event.content_hash(), renamed.content_hash());
+
+        let rome = parse_event(&["DTSTART;TZID=Europe/Rome:20220106T100000"]);
+        let berlin = parse_event(&["DTSTART;TZID=Europe/Berlin:20220106T100000"]);
+        assert_eq!(rome.dt_start, berlin.dt_start);
+        assert_ne!(rome.content_hash(), berlin.content_hash());
+
+        // persisted values must not change between releases
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn period_non_recurring() {
+        let event = parse_event(&["DTSTART:20220106T100000Z", "DTEND:20220106T110000Z"]);
+
+        assert_eq!(event.period(), event.into_iter().next().unwrap());
+        assert_eq!(event.into_iter().count(), 1);
+    }
+
+    #[test]
+    fn duration_timed() {
+        let event = parse_event(&["DTSTART:20220106T100000Z", "DTEND:20220106T110000Z"]);
+
+        assert_eq!(event.duration(), chrono::Duration::hours(1));
+    }
+
+    #[test]
+    fn duration_property() {
+        let day = |d| DateOrDateTime::WholeDay(Utc.with_ymd_and_hms(2022, 1, d, 0, 0, 0).unwrap());
+
+        let event = parse_event(&[
+            "DTSTART;VALUE=DATE:20220103",
+            "DURATION:P1D",
+            "RRULE:FREQ=WEEKLY;COUNT=3",
+        ]);
+        assert_eq!(event.duration, Some(chrono::Duration::days(1)));
+        assert_eq!(event.dt_end, day(4));
+        assert_eq!(
+            event.into_iter().collect::<Vec<_>>(),
+            [day(3)..day(4), day(10)..day(11), day(17)..day(18)]
+        );
+
+        let event = parse_event(&["DTSTART:20220106T100000Z", "DURATION:PT1H30M"]);
+        assert_eq!(event.duration(), chrono::Duration::minutes(90));
+        let block = Block::from(&event);
+        assert!(block.inner_lines.contains(&"DURATION:PT1H30M".to_owned()));
+        assert!(!block
+            .inner_lines
+            .iter()
+            .any(|line| line.starts_with("DTEND")));
+    }
+
+    #[test]
+    fn duration_and_dtend() {
+        let lines = [
+            "BEGIN:VEVENT",
+            "UID:both",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "DTEND:20220106T110000Z",
+            "DURATION:PT2H",
+            "END:VEVENT",
+        ];
+        let block = || Block::try_from(lines.map(str::to_owned).as_slice()).unwrap();
+
+        assert!(matches!(
+            VEvent::from_block(block(), &ParseOptions::strict()),
+            Err(VEventFormatError::EndAndDuration { .. })
+        ));
+
+        // DTEND wins for both the end and the duration
+        let event = VEvent::from_block(block(), &ParseOptions::lenient()).unwrap();
+        assert_eq!(event.duration, None);
+        assert_eq!(
+            event.dt_end,
+            DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, 6, 11, 0, 0).unwrap())
+        );
+        assert_eq!(event.duration(), chrono::Duration::hours(1));
+    }
+
+    #[test]
+    fn duration_whole_day() {
+        let event = parse_event(&["DTSTART;VALUE=DATE:20220101", "DTEND;VALUE=DATE:20220104"]);
+        assert_eq!(event.duration(), chrono::Duration::days(3));
+
+        let event = parse_event(&["DTSTART;VALUE=DATE:20220101"]);
+        assert_eq!(event.duration(), chrono::Duration::days(1));
+    }
+
+    #[test]
+    fn whole_day_single_day_occurrence() {
+        let event = parse_event(&["DTSTART;VALUE=DATE:20220101", "DTEND;VALUE=DATE:20220102"]);
+        let day = |d| DateOrDateTime::WholeDay(Utc.with_ymd_and_hms(2022, 1, d, 0, 0, 0).unwrap());
+
+        let occurrence = event.into_iter().next().unwrap();
+        assert_eq!(
+            DateOrDateTime::inclusive_end(occurrence.start, occurrence.end),
+            day(1)
+        );
+
+        let result = event.next_occurrence_since(day(1)).unwrap().unwrap();
+        assert_eq!(result.event_overlap, EventOverlap::StartSameDayEndsSameDay);
+        assert!(event.next_occurrence_since(day(2)).unwrap().is_none());
+    }
+
+    #[test]
+    fn whole_day_multi_day_occurrence() {
+        let event = parse_event(&["DTSTART;VALUE=DATE:20220101", "DTEND;VALUE=DATE:20220104"]);
+        let day = |d| DateOrDateTime::WholeDay(Utc.with_ymd_and_hms(2022, 1, d, 0, 0, 0).unwrap());
+
+        let occurrence = event.into_iter().next().unwrap();
+        assert_eq!(
+            DateOrDateTime::inclusive_end(occurrence.start, occurrence.end),
+            day(3)
+        );
+
+        let result = event.next_occurrence_since(day(2)).unwrap().unwrap();
+        assert_eq!(result.event_overlap, EventOverlap::StartsPastEndsFuture);
+        let result = event.next_occurrence_since(day(3)).unwrap().unwrap();
+        assert_eq!(result.event_overlap, EventOverlap::StartsPastEndsSameDay);
+        assert!(event.next_occurrence_since(day(4)).unwrap().is_none());
+    }
+
+    #[test]
+    fn find_occurrence_on_weekend() {
+        // Monday
+        let event = parse_event(&[
+            "DTSTART:20220103T100000Z",
+            "DTEND:20220103T110000Z",
+            "RRULE:FREQ=DAILY",
+        ]);
+        let is_weekend = |o: &Range<DateOrDateTime>| {
+            matches!(
+                o.start.date().weekday(),
+                chrono::Weekday::Sat | chrono::Weekday::Sun
+            )
+        };
+
+        let occurrence = event.find_occurrence(is_weekend, 100).unwrap();
+        assert_eq!(
+            occurrence.start,
+            DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, 8, 10, 0, 0).unwrap())
+        );
+
+        assert!(event.find_occurrence(is_weekend, 5).is_none());
+    }
+
+    #[test]
+    fn color() {
+        let event = parse_event(&["DTSTART:20220106T100000Z", "COLOR:red"]);
+        assert_eq!(event.color.as_deref(), Some("red"));
+
+        let event = parse_event(&["DTSTART:20220106T100000Z"]);
+        assert_eq!(event.color, None);
+    }
+
+    #[test]
+    fn comments() {
+        let event = parse_event(&[
+            "DTSTART:20220106T100000Z",
+            "COMMENT:First\\, with comma",
+            "COMMENT:Second\\nline",
+        ]);
+
+        assert_eq!(event.comments, ["First, with comma", "Second\nline"]);
+
+        let event = parse_event(&[
+            "DTSTART:20220106T100000Z",
+            "COMMENT:plain",
+            "COMMENT;LANGUAGE=en:hello\\, world",
+        ]);
+        assert_eq!(event.comments, ["plain", "hello, world"]);
+    }
+
+    #[test]
+    fn attachments() {
+        let event = parse_event(&[
+            "DTSTART:20220106T100000Z",
+            "ATTACH:https://example.com/agenda.pdf?a=1;b=2",
+            "ATTACH;FMTTYPE=text/plain;ENCODING=BASE64;VALUE=BINARY:aGVsbG8=",
+            "ATTACH;FMTTYPE=application/pdf:https://example.com/minutes.pdf",
+        ]);
+
+        assert_eq!(
+            event.attachments,
+            [
+                Attachment::Uri {
+                    uri: "https://example.com/agenda.pdf?a=1;b=2".to_owned(),
+                    fmt_type: None
+                },
+                Attachment::Binary {
+                    data: b"hello".to_vec(),
+                    fmt_type: Some("text/plain".to_owned())
+                },
+                Attachment::Uri {
+                    uri: "https://example.com/minutes.pdf".to_owned(),
+                    fmt_type: Some("application/pdf".to_owned())
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn exdate_without_tzid() {
+        let event = parse_event(&[
+            "DTSTART:20220101T000000Z",
+            "RRULE:FREQ=DAILY;COUNT=4",
+            "EXDATE:20220102T000000Z",
+            "EXDATE:20220103T000000",
+        ]);
+
+        assert_eq!(event.exdates.len(), 2);
+        assert_eq!(
+            event.exdates[0].date_time,
+            DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, 2, 0, 0, 0).unwrap())
+        );
+        assert!(matches!(
+            event.exdates[1].date_time,
+            DateOrDateTime::Floating(_)
+        ));
+        assert_eq!(event.into_iter().count(), 2);
+    }
+
+    #[test]
+    fn exdate_list() {
+        let event = parse_event(&[
+            "DTSTART;VALUE=DATE:20220101",
+            "RRULE:FREQ=WEEKLY;COUNT=5",
+            "EXDATE;VALUE=DATE:20220108,20220115,20220122",
+        ]);
+
+        assert_eq!(event.exdates.len(), 3);
+        assert!(event
+            .exdates
+            .iter()
+            .all(|e| matches!(e.date_time, DateOrDateTime::WholeDay(_))));
+        assert_eq!(event.into_iter().count(), 2);
+    }
+
+    #[test]
+    fn exdate_value_date_time() {
+        let at =
+            |d, h| DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, d, h, 0, 0).unwrap());
+        let event = parse_event(&[
+            "DTSTART:20220101T100000Z",
+            "RRULE:FREQ=DAILY;COUNT=5",
+            "EXDATE;VALUE=DATE-TIME:20220102T100000Z,20220103T100000Z",
+            "EXDATE;VALUE=DATE-TIME;TZID=Europe/Rome:20220104T110000",
+        ]);
+
+        assert_eq!(
+            event
+                .exdates
+                .iter()
+                .map(|exdate| exdate.date_time)
+                .collect::<Vec<_>>(),
+            [at(2, 10), at(3, 10), at(4, 10)]
+        );
+        assert_eq!(event.exdates[2].time_zone, chrono_tz::Europe::Rome);
+        assert_eq!(
+            event.into_iter().map(|o| o.start).collect::<Vec<_>>(),
+            [at(1, 10), at(5, 10)]
+        );
+    }
+
+    #[test]
+    fn to_block_round_trip() {
+        let event = parse_event(&[
+            "UID:1234@example.com",
+            "DTSTART;VALUE=DATE:20220101",
+            "DTEND;VALUE=DATE:20220103",
+            "DESCRIPTION:Line\\none",
+            "RRULE:FREQ=WEEKLY;BYDAY=MO,FR;COUNT=10",
+            "EXDATE;TZID=Europe/Rome:20220106T154000",
+            "EXDATE;VALUE=DATE:20220110",
+            "STATUS:CONFIRMED",
+            "ORGANIZER;CN=John:mailto:john@example.com",
+            "X-GOOGLE-CONFERENCE:https://meet.google.com/abc",
+            "COLOR:red",
+            "COMMENT:First\\, with comma",
+            "ATTACH;FMTTYPE=text/plain;ENCODING=BASE64;VALUE=BINARY:aGVsbG8=",
+            "ATTACH:https://example.com/agenda.pdf",
+        ]);
+
+        let block = Block::from(&event);
+        assert_eq!(block.name(), "VEVENT");
+        let parsed = VEvent::try_from(block).unwrap();
+
+        assert_eq!(parsed.uid, event.uid);
+        assert_eq!(parsed.dt_created, event.dt_created);
+        assert_eq!(parsed.dt_last_modified, event.dt_last_modified);
+        assert_eq!(parsed.dt_stamp, event.dt_stamp);
+        assert_eq!(parsed.dt_start, event.dt_start);
+        assert_eq!(parsed.dt_end, event.dt_end);
+        assert_eq!(parsed.summary, event.summary);
+        assert_eq!(parsed.description, event.description);
+        assert_eq!(
+            parsed.rrule.as_ref().map(|r| r.to_rrule_string()),
+            event.rrule.as_ref().map(|r| r.to_rrule_string())
+        );
+        assert_eq!(
+            parsed
+                .exdates
+                .iter()
+                .map(|e| (e.time_zone, e.date_time))
+                .collect::<Vec<_>>(),
+            event
+                .exdates
+                .iter()
+                .map(|e| (e.time_zone, e.date_time))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(parsed.sequence, event.sequence);
+        assert_eq!(parsed.status, event.status);
+        assert_eq!(parsed.organizer, event.organizer);
+        assert_eq!(parsed.google_conference_url, event.google_conference_url);
+        assert_eq!(parsed.color, event.color);
+        assert_eq!(parsed.comments, event.comments);
+        assert_eq!(parsed.attachments, event.attachments);
+
+        // no SUMMARY, DTSTART and DTEND in named zones
+        let block: Block = [
+            "BEGIN:VEVENT",
+            "UID:zoned",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART;TZID=Europe/Rome:20220106T100000",
+            "DTEND;TZID=Europe/Berlin:20220106T110000",
+            "END:VEVENT",
+        ]
+        .map(str::to_owned)
+        .as_slice()
+        .try_into()
+        .unwrap();
+        let event = VEvent::try_from(block).unwrap();
+        let block = Block::from(&event);
+        assert!(block
+            .inner_lines
+            .contains(&"DTSTART;TZID=Europe/Rome:20220106T100000".to_owned()));
+        let parsed = VEvent::try_from(block).unwrap();
+
+        assert_eq!(parsed.summary, "");
+        assert_eq!(parsed.dt_start, event.dt_start);
+        assert_eq!(parsed.dt_end, event.dt_end);
+        assert_eq!(parsed.dt_start_tzid.as_deref(), Some("Europe/Rome"));
+        assert_eq!(parsed.dt_end_tzid.as_deref(), Some("Europe/Berlin"));
+    }
+
+    #[test]
+    fn dtstart_value_date_time_tzid() {
+        let event = parse_event(&[
+            "DTSTART;VALUE=DATE-TIME;TZID=Europe/Rome:20220106T154000",
+            "DTEND;TZID=Europe/Rome;VALUE=DATE-TIME:20220106T164000",
+        ]);
+
+        assert_eq!(
+            event.dt_start,
+            DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, 6, 14, 40, 0).unwrap())
+        );
+        assert_eq!(
+            event.dt_end,
+            DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, 6, 15, 40, 0).unwrap())
+        );
+
+        // parameter names and values ignore case
+        let event = parse_event(&[
+            "DTSTART;value=date-time;tzid=Europe/Rome:20220106T154000",
+            "DTEND;Value=Date:20220107",
+        ]);
+        assert_eq!(
+            event.dt_start,
+            DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, 6, 14, 40, 0).unwrap())
+        );
+        assert_eq!(event.dt_start_tzid.as_deref(), Some("Europe/Rome"));
+        assert!(matches!(event.dt_end, DateOrDateTime::WholeDay(_)));
+    }
+
+    #[test]
+    fn priority_level() {
+        let level = |priority: &str| parse_event(&["DTSTART:20220101T000000Z", priority]);
+
+        assert_eq!(level("PRIORITY:4").priority_level(), PriorityLevel::High);
+        assert_eq!(level("PRIORITY:5").priority_level(), PriorityLevel::Medium);
+        assert_eq!(level("PRIORITY:6").priority_level(), PriorityLevel::Low);
+        assert_eq!(
+            level("PRIORITY:0").priority_level(),
+            PriorityLevel::Undefined
+        );
+        assert_eq!(level("X-NONE:1").priority_level(), PriorityLevel::Undefined);
+
+        let event = level("PRIORITY:1");
+        assert_eq!(event.priority, Some(1));
+        let block = Block::from(&event);
+        assert!(block.inner_lines.iter().any(|l| l == "PRIORITY:1"));
+
+        let lines = [
+            "BEGIN:VEVENT",
+            "CREATED:20220101T000000Z",
+            "LAST-MODIFIED:20220101T000000Z",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220101T000000Z",
+            "SEQUENCE:0",
+            "SUMMARY:Test",
+            "PRIORITY:10",
+            "END:VEVENT",
+        ]
+        .map(str::to_owned);
+        let block: Block = lines.as_slice().try_into().unwrap();
+        assert!(matches!(
+            VEvent::try_from(block),
+            Err(VEventFormatError::InvalidPriority { .. })
+        ));
+    }
+
+    #[test]
+    fn occurrences_with_overlap() {
+        let day = |d| DateOrDateTime::WholeDay(Utc.with_ymd_and_hms(2022, 1, d, 0, 0, 0).unwrap());
+
+        // two days long, DTEND is exclusive
+        let event = parse_event(&[
+            "DTSTART;VALUE=DATE:20220101",
+            "DTEND;VALUE=DATE:20220103",
+            "RRULE:FREQ=WEEKLY;COUNT=3",
+        ]);
+
+        let overlaps = event
+            .occurrences_with_overlap(day(2))
+            .map(|o| (o.occurrence.start, o.event_overlap, o.index))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            overlaps,
+            [
+                (day(1), EventOverlap::StartsPastEndsSameDay, 1),
+                (day(8), EventOverlap::StartsFuture, 2),
+                (day(15), EventOverlap::StartsFuture, 3),
+            ]
+        );
+
+        let first = event.occurrences_with_overlap(day(8)).next().unwrap();
+        let expected = event.next_occurrence_since(day(8)).unwrap().unwrap();
+        assert_eq!(first.occurrence, expected.occurrence);
+        assert_eq!(first.event_overlap, EventOverlap::StartsSameDayEndsFuture);
+        assert_eq!((first.index, expected.index), (2, 2));
+
+        assert_eq!(event.occurrences_with_overlap(day(20)).count(), 0);
+    }
+
+    #[test]
+    fn fractional_seconds() {
+        let event = parse_event(&["DTSTART:20220101T120000.000Z", "DTEND:20220101T130000.5Z"]);
+
+        assert_eq!(
+            event.dt_start,
+            DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, 1, 12, 0, 0).unwrap())
+        );
+        assert_eq!(
+            event.dt_end,
+            DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, 1, 13, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn floating_fractional_seconds() {
+        let event = parse_event(&["DTSTART:20220101T120000.250", "DTEND:20220101T130000"]);
+        let noon = chrono::NaiveDate::from_ymd_opt(2022, 1, 1)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap();
+
+        assert_eq!(event.dt_start, DateOrDateTime::Floating(noon));
+        assert_eq!(
+            event.dt_end,
+            DateOrDateTime::Floating(noon + chrono::Duration::hours(1))
+        );
+        assert_eq!(
+            event.dt_start.to_string(),
+            "20220101T120000",
+            "discarded fractions must not leak into the serialization"
+        );
+    }
+
+    #[test]
+    fn next_occurrence_skipping_cancelled() {
+        let dt = DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, 1, 0, 0, 0).unwrap());
+        let event = parse_event(&["DTSTART:20220102T100000Z", "STATUS:cancelled"]);
+
+        assert!(event.is_cancelled());
+        assert!(event.next_occurrence_since(dt).unwrap().is_some());
+        assert!(event
+            .next_occurrence_since_skipping_cancelled(dt)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn hash_by_uid_and_recurrence_id() {
+        use std::collections::HashSet;
+
+        let revision = |sequence: &str, dt_stamp: &str| {
+            parse_event(&[
+                "UID:standup",
+                "DTSTART:20220103T090000Z",
+                "RRULE:FREQ=DAILY",
+                sequence,
+                dt_stamp,
+            ])
+        };
+        let first = revision("SEQUENCE:0", "DTSTAMP:20220101T000000Z");
+        let second = revision("SEQUENCE:1", "DTSTAMP:20220102T000000Z");
+        let moved = parse_event(&[
+            "UID:standup",
+            "RECURRENCE-ID;TZID=Europe/Rome:20220105T100000",
+            "DTSTART:20220105T100000Z",
+        ]);
+        assert_eq!(
+            moved.recurrence_id,
+            Some(DateOrDateTime::DateTime(
+                Utc.with_ymd_and_hms(2022, 1, 5, 9, 0, 0).unwrap()
+            ))
+        );
+
+        let hash = |event: &VEvent| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            event.hash(&mut hasher);
+            hasher.finish()
+        };
+        assert_ne!(first, second);
+        assert_eq!(hash(&first), hash(&second));
+        assert_ne!(hash(&first), hash(&moved));
+
+        let set = [first.clone(), first, second, moved]
+            .into_iter()
+            .collect::<HashSet<_>>();
+        assert_eq!(set.len(), 3);
+    }
+
+    #[test]
+    fn sequence_defaults_to_zero() {
+        let lines = [
+            "BEGIN:VEVENT",
+            "CREATED:20220101T000000Z",
+            "LAST-MODIFIED:20220101T000000Z",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220101T000000Z",
+            "SUMMARY:Test",
+            "END:VEVENT",
+        ]
+        .map(str::to_owned);
+        let block: Block = lines.as_slice().try_into().unwrap();
+
+        assert_eq!(VEvent::try_from(block).unwrap().sequence, 0);
+    }
+
+    #[test]
+    fn end_before_start() {
+        let lines = [
+            "BEGIN:VEVENT",
+            "DTSTAMP:20220101T000000Z",
+            "DTSTART:20220106T100000Z",
+            "DTEND:20220106T090000Z",
+            "END:VEVENT",
+        ]
+        .map(str::to_owned);
+        let block: Block = lines.as_slice().try_into().unwrap();
+
+        match VEvent::try_from(block) {
+            Err(VEventFormatError::EndBeforeStart { dt_start, dt_end }) => {
+                assert_eq!(
+                    dt_start,
+                    DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, 6, 10, 0, 0).unwrap())
+                );
+                assert_eq!(
+                    dt_end,
+                    DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, 6, 9, 0, 0).unwrap())
+                );
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[test]
+    fn iter_from() {
+        let at =
+            |y, m, d, h| DateOrDateTime::DateTime(Utc.with_ymd_and_hms(y, m, d, h, 0, 0).unwrap());
+
+        for lines in [
+            ["DTEND:20220106T110000Z", "RRULE:FREQ=DAILY"],
+            ["DTEND:20220106T110000Z", "RRULE:FREQ=WEEKLY;INTERVAL=3"],
+            [
+                "DTEND:20220107T110000Z",
+                "RRULE:FREQ=DAILY;INTERVAL=2;COUNT=500",
+            ],
+            ["DTEND:20220106T100000Z", "RRULE:FREQ=MONTHLY;BYMONTHDAY=6"],
+        ] {
+            let event = parse_event(&["DTSTART:20220106T100000Z", lines[0], lines[1]]);
+
+            for from in [
+                at(2021, 1, 1, 0),
+                at(2022, 1, 6, 10),
+                at(2022, 3, 1, 10),
+                at(2023, 5, 8, 10),
+                at(2024, 2, 29, 12),
+            ] {
+                let expected = event
+                    .into_iter()
+                    .take(1000)
+                    .find(|o| o.end > from || o.start >= from);
+                assert_eq!(
+                    event.iter_from(from).next(),
+                    expected,
+                    "{lines:?} from {from:?}"
+                );
+            }
+        }
+
+        // next_occurrence_since fast forwards too, keeping the index right
+        let event = parse_event(&["DTSTART:20220106T100000Z", "RRULE:FREQ=DAILY"]);
+        let result = event
+            .next_occurrence_since(at(2032, 1, 6, 0))
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.occurrence.start, at(2032, 1, 6, 10));
+        assert_eq!(result.index, 3653);
+    }
+
+    #[test]
+    fn next_occurrence_since_fast_forward() {
+        let at =
+            |y, m, d| DateOrDateTime::DateTime(Utc.with_ymd_and_hms(y, m, d, 0, 0, 0).unwrap());
+
+        // generating the occurrences one by one would take millions of steps
+        for (rrule, expected) in [
+            ("RRULE:FREQ=DAILY", at(100_000, 1, 1)),
+            ("RRULE:FREQ=WEEKLY", at(100_000, 1, 6)),
+            ("RRULE:FREQ=MONTHLY;BYMONTHDAY=6", at(100_000, 1, 6)),
+            ("RRULE:FREQ=YEARLY", at(100_000, 1, 6)),
+        ] {
+            let event = parse_event(&["DTSTART:20220106T100000Z", rrule]);
+            let result = event
+                .next_occurrence_since(at(100_000, 1, 1))
+                .unwrap()
+                .unwrap();
+            assert_eq!(
+                result.occurrence.start.date().date_naive(),
+                expected.date().date_naive(),
+                "{rrule}"
+            );
+        }
+
+        // and it finds the same occurrence as iterating one by one
+        let event = parse_event(&[
+            "DTSTART:20120131T100000Z",
+            "DTEND:20120202T100000Z",
+            "RRULE:FREQ=MONTHLY;BYMONTHDAY=1;COUNT=200",
+        ]);
+        for dt in [
+            at(2012, 2, 2),
+            at(2020, 3, 2),
+            at(2020, 3, 3),
+            at(2028, 8, 3),
+            at(2040, 1, 1),
+        ] {
+            let expected = event
+                .into_iter()
+                .position(|o| {
+                    dt.intersects_day_aware(o.start, o.end).unwrap() != EventOverlap::FinishesPast
+                })
+                .map(|index| index as u32 + 1);
+            let result = event.next_occurrence_since(dt).unwrap();
+            assert_eq!(result.map(|r| r.index), expected, "{dt:?}");
+        }
+    }
+
+    #[test]
+    fn quoted_parameters() {
+        let event = parse_event(&[
+            r#"DTSTART;TZID="Europe/Rome":20220106T100000"#,
+            r#"ORGANIZER;CN="Doe, John: Sales":mailto:john@example.com"#,
+            r#"ATTACH;FMTTYPE="text/plain";X-LABEL="a:b":https://example.com/a.txt"#,
+        ]);
+
+        assert_eq!(
+            event.dt_start,
+            DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, 6, 9, 0, 0).unwrap())
+        );
+        assert_eq!(event.dt_start_tzid.as_deref(), Some("Europe/Rome"));
+        assert_eq!(
+            event.organizer.as_deref(),
+            Some(r#"CN="Doe, John: Sales":mailto:john@example.com"#)
+        );
+        assert_eq!(
+            event.attachments,
+            [Attachment::Uri {
+                uri: "https://example.com/a.txt".to_owned(),
+                fmt_type: Some("text/plain".to_owned()),
+            }]
+        );
+    }
+
+    #[test]
+    fn trim_values() {
+        let block: Block = [
+            "BEGIN:VEVENT",
+            "DTSTART:20220106T100000Z",
+            "SUMMARY: Meeting ",
+            "DESCRIPTION:  Agenda",
+            "COMMENT:Notes ",
+            "END:VEVENT",
+        ]
+        .map(str::to_owned)
+        .as_slice()
+        .try_into()
+        .unwrap();
+
+        let event = VEvent::from_block(block.clone(), &ParseOptions::default()).unwrap();
+        assert_eq!(event.summary, " Meeting ");
+        assert_eq!(event.description.as_deref(), Some("  Agenda"));
+
+        let event = VEvent::from_block(block, &ParseOptions::lenient().trim_values(true)).unwrap();
+        assert_eq!(event.summary, "Meeting");
+        assert_eq!(event.description.as_deref(), Some("Agenda"));
+        assert_eq!(event.comments, ["Notes"]);
+    }
+
+    #[test]
+    fn local_range() {
+        let rome = chrono_tz::Europe::Rome;
+        let event = parse_event(&["DTSTART:20220701T130000Z", "DTEND:20220701T140000Z"]);
+        let range = event.local_range(&rome);
+        assert_eq!(
+            range,
+            rome.with_ymd_and_hms(2022, 7, 1, 15, 0, 0).unwrap()
+                ..rome.with_ymd_and_hms(2022, 7, 1, 16, 0, 0).unwrap()
+        );
+        assert_eq!(
+            range.start.format("%-I:%M %p %Z").to_string(),
+            "3:00 PM CEST"
+        );
+
+        // floating times and whole days keep their wall clock time
+        let event = parse_event(&["DTSTART:20220701T090000", "DTEND:20220701T100000"]);
+        assert_eq!(
+            event.local_range(&rome).start,
+            rome.with_ymd_and_hms(2022, 7, 1, 9, 0, 0).unwrap()
+        );
+        let event = parse_event(&["DTSTART;VALUE=DATE:20220327"]);
+        assert_eq!(
+            event.local_range(&rome),
+            rome.with_ymd_and_hms(2022, 3, 27, 0, 0, 0).unwrap()
+                ..rome.with_ymd_and_hms(2022, 3, 27, 0, 0, 0).unwrap()
+        );
+
+        // 02:30 doesn't exist on the day daylight saving time starts
+        let event = parse_event(&["DTSTART:20220327T023000"]);
+        assert_eq!(
+            event.local_range(&rome).start,
+            rome.with_ymd_and_hms(2022, 3, 27, 3, 30, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn utc_metadata() {
+        let noon = DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, 1, 12, 0, 0).unwrap());
+        let lines = [
+            "BEGIN:VEVENT",
+            "UID:utc",
+            "DTSTART:20220106T100000Z",
+            "DTSTAMP:20220101T120000",
+            "CREATED:20220101T120000Z",
+            "LAST-MODIFIED:20220101",
+            "END:VEVENT",
+        ];
+        let block = || Block::try_from(lines.map(str::to_owned).as_slice()).unwrap();
+
+        let event = VEvent::from_block(block(), &ParseOptions::lenient()).unwrap();
+        assert_eq!(event.dt_stamp, noon);
+        assert_eq!(event.dt_created, noon);
+        assert_eq!(
+            event.dt_last_modified,
+            DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, 1, 0, 0, 0).unwrap())
+        );
+
+        assert!(matches!(
+            VEvent::from_block(block(), &ParseOptions::strict()),
+            Err(VEventFormatError::NotUtc { property, .. }) if property == "DTSTAMP"
+        ));
+    }
+
+    #[test]
+    fn unknown_tzid_in_date_lists() {
+        let floating = |day| {
+            DateOrDateTime::Floating(
+                chrono::NaiveDate::from_ymd_opt(2022, 1, day)
+                    .unwrap()
+                    .and_hms_opt(10, 0, 0)
+                    .unwrap(),
+            )
+        };
+
+        for (line, dates) in [
+            (
+                "EXDATE;TZID=Mars/Olympus:20220107T100000,20220108T100000",
+                [7, 8],
+            ),
+            (
+                "RDATE;TZID=Mars/Olympus:20220110T100000,20220111T100000",
+                [10, 11],
+            ),
+            (
+                "RDATE;VALUE=PERIOD;TZID=Mars/Olympus:20220110T100000/PT1H,20220111T100000/PT1H",
+                [10, 11],
+            ),
+            (
+                "RDATE;value=period;tzid=Mars/Olympus:20220110T100000/PT1H,20220111T100000/PT1H",
+                [10, 11],
+            ),
+        ] {
+            let lines = [
+                "BEGIN:VEVENT",
+                "UID:unknown-tzid",
+                "DTSTAMP:20220101T000000Z",
+                "DTSTART:20220106T100000",
+                line,
+                "END:VEVENT",
+            ];
+            let block = || Block::try_from(lines.map(str::to_owned).as_slice()).unwrap();
+
+            let event = VEvent::from_block(block(), &ParseOptions::lenient()).unwrap();
+            let parsed = event
+                .exdates
+                .iter()
+                .map(|exdate| exdate.date_time)
+                .chain(event.rdates.iter().map(RDate::start))
+                .collect::<Vec<_>>();
+            assert_eq!(parsed, dates.map(floating), "{line}");
+            // the TZID is kept to be reported
+            assert_eq!(
+                event.referenced_tzids().collect::<Vec<_>>(),
+                ["Mars/Olympus"; 2],
+                "{line}"
+            );
+
+            assert!(matches!(
+                VEvent::from_block(block(), &ParseOptions::strict()),
+                Err(VEventFormatError::TzIdDateTimeFormatError(
+                    TzIdDateTimeFormatError::UnknownTimeZone { tzid }
+                )) if tzid == "Mars/Olympus"
+            ));
+        }
+    }
+
+    #[test]
+    fn occurrence_count() {
+        for (lines, count) in [
+            (&["RRULE:FREQ=DAILY"][..], None),
+            (&["RRULE:FREQ=DAILY;COUNT=12"], Some(12)),
+            (&["RRULE:FREQ=DAILY;UNTIL=20220110T100000Z"], Some(5)),
+            (
+                &["RRULE:FREQ=DAILY;COUNT=3;UNTIL=20220110T100000Z"],
+                Some(3),
+            ),
+            (
+                &[
+                    "RRULE:FREQ=WEEKLY;UNTIL=20220131T100000Z",
+                    "EXDATE:20220113T100000Z",
+                ],
+                Some(4),
+            ),
+            (&["RRULE:FREQ=MONTHLY;BYSETPOS=-1"], None),
+            (&[], Some(1)),
+            (&["RDATE:20220107T100000Z,20220108T100000Z"], Some(3)),
+            // computed from the step
+            (&["RRULE:FREQ=DAILY;UNTIL=20991231T235959Z"], Some(28_484)),
+            (
+                &["RRULE:FREQ=WEEKLY;INTERVAL=2;UNTIL=20220203T100000Z"],
+                Some(3),
+            ),
+            (&["RRULE:FREQ=MONTHLY;BYMONTHDAY=6;UNTIL=20220606"], Some(6)),
+            (
+                &["RRULE:FREQ=YEARLY;COUNT=10;UNTIL=99991231T235959Z"],
+                Some(10),
+            ),
+            (
+                &["RRULE:FREQ=SECONDLY;UNTIL=20991231T235959Z"],
+                Some(2_460_981_600),
+            ),
+            // too many to generate
+            (
+                &[
+                    "RRULE:FREQ=DAILY;UNTIL=99991231T235959Z",
+                    "EXDATE:20220107T100000Z",
+                ],
+                None,
+            ),
+        ] {
+            let mut all_lines = vec!["DTSTART:20220106T100000Z"];
+            all_lines.extend(lines);
+            assert_eq!(
+                parse_event(&all_lines).occurrence_count(),
+                count,
+                "{lines:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn property_order() {
+        let lines = [
+            "BEGIN:VEVENT",
+            "SUMMARY:Standup",
+            "DTSTART:20220106T100000Z",
+            "DTEND:20220106T103000Z",
+            "RRULE:FREQ=DAILY;COUNT=5",
+            "EXDATE:20220107T100000Z",
+            "UID:standup",
+            "DTSTAMP:20220101T000000Z",
+            "X-UNKNOWN:kept out",
+            "EXDATE:20220108T100000Z",
+            "SEQUENCE:0",
+            "END:VEVENT",
+        ];
+        let event = VEvent::try_from(Block::try_from(lines.map(str::to_owned).as_slice()).unwrap())
+            .unwrap();
+        assert_eq!(
+            event.property_order,
+            ["SUMMARY", "DTSTART", "DTEND", "RRULE", "EXDATE", "UID", "DTSTAMP", "SEQUENCE"]
+        );
+
+        // CREATED and LAST-MODIFIED were not in the source, they go last
+        let block = Block::from(&event);
+        assert_eq!(
+            block.inner_lines,
+            [
+                "SUMMARY:Standup",
+                "DTSTART:20220106T100000Z",
+                "DTEND:20220106T103000Z",
+                "RRULE:FREQ=DAILY;COUNT=5",
+                "EXDATE:20220107T100000Z",
+                "EXDATE:20220108T100000Z",
+                "UID:standup",
+                "DTSTAMP:20220101T000000Z",
+                "SEQUENCE:0",
+                "CREATED:20220101T000000Z",
+                "LAST-MODIFIED:20220101T000000Z",
+            ]
+        );
+    }
+
+    #[test]
+    fn language() {
+        let event = parse_event(&[
+            "DTSTART:20220106T100000Z",
+            "SUMMARY;LANGUAGE=en-US:Meeting",
+            "DESCRIPTION;ALTREP=\"cid:part1@example.org\";language=it:Riunione",
+        ]);
+        assert_eq!(event.summary, "Meeting");
+        assert_eq!(event.summary_language.as_deref(), Some("en-US"));
+        assert_eq!(event.description.as_deref(), Some("Riunione"));
+        assert_eq!(event.description_language.as_deref(), Some("it"));
+
+        let block = Block::from(&event);
+        assert!(block
+            .inner_lines
+            .contains(&"SUMMARY;LANGUAGE=en-US:Meeting".to_owned()));
+        let parsed = VEvent::try_from(block).unwrap();
+        assert_eq!(parsed.summary_language, event.summary_language);
+        assert_eq!(parsed.description_language, event.description_language);
+        assert_eq!(parsed.description_altrep, event.description_altrep);
+
+        let event = parse_event(&["DTSTART:20220106T100000Z", "SUMMARY:Meeting"]);
+        assert_eq!(event.summary_language, None);
+    }
+
+    #[test]
+    fn description_altrep() {
+        let event = parse_event(&[
+            "DTSTART:20220106T100000Z",
+            "DESCRIPTION;ALTREP=\"cid:part1.0001@example.org\":The agenda, in HTML",
+        ]);
+        assert_eq!(event.description.as_deref(), Some("The agenda, in HTML"));
+        assert_eq!(
+            event.description_altrep.as_deref(),
+            Some("cid:part1.0001@example.org")
+        );
+
+        let block = Block::from(&event);
+        assert!(block.inner_lines.contains(
+            &"DESCRIPTION;ALTREP=\"cid:part1.0001@example.org\":The agenda, in HTML".to_owned()
+        ));
+    }
 }
@@ -0,0 +1,262 @@
+use crate::{
+    rrule::{Options, RRule},
+    Attachment, DateOrDateTime, RDate, TzIdDateTime, VEvent,
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum VEventBuildError {
+    #[error("RRULE UNTIL {until:?} must have the same value type as DTSTART {dt_start:?}")]
+    UntilValueTypeMismatch {
+        dt_start: DateOrDateTime,
+        until: DateOrDateTime,
+    },
+    #[error("DTEND {dt_end:?} is before DTSTART {dt_start:?}")]
+    EndBeforeStart {
+        dt_start: DateOrDateTime,
+        dt_end: DateOrDateTime,
+    },
+    #[error("DTSTART {dt_start:?} plus DURATION {duration:?} is out of range")]
+    EndOutOfRange {
+        dt_start: DateOrDateTime,
+        duration: chrono::Duration,
+    },
+}
+
+/// Builds a [`VEvent`] programmatically. `CREATED`, `LAST-MODIFIED` and `DTSTAMP` default to
+/// now, `DTEND` to `DTSTART` and `SEQUENCE` to 0.
+#[derive(Debug, Clone)]
+pub struct VEventBuilder {
+    event: VEvent,
+}
+
+impl VEventBuilder {
+    pub fn new(dt_start: DateOrDateTime, summary: impl Into<String>) -> Self {
+        let now = DateOrDateTime::now();
+
+        Self {
+            event: VEvent {
+                uid: None,
+                recurrence_id: None,
+                dt_created: now,
+                dt_last_modified: now,
+                dt_start,
+                dt_end: dt_start,
+                duration: None,
+                dt_start_tzid: None,
+                dt_end_tzid: None,
+                recurrence_id_tzid: None,
+                dt_stamp: now,
+                summary: summary.into(),
+                summary_language: None,
+                description: None,
+                description_language: None,
+                description_altrep: None,
+                rrule: None,
+                exdates: Vec::new(),
+                rdates: Vec::new(),
+                sequence: 0,
+                priority: None,
+                status: None,
+                organizer: None,
+                google_conference_url: None,
+                color: None,
+                comments: Vec::new(),
+                attachments: Vec::new(),
+                property_order: Vec::new(),
+            },
+        }
+    }
+
+    pub fn uid(mut self, uid: impl Into<String>) -> Self {
+        self.event.uid = Some(uid.into());
+        self
+    }
+
+    pub fn dt_end(mut self, dt_end: DateOrDateTime) -> Self {
+        self.event.dt_end = dt_end;
+        self.event.duration = None;
+        self
+    }
+
+    /// Sets `DURATION` instead of `DTEND`. The end is computed when building.
+    pub fn duration(mut self, duration: chrono::Duration) -> Self {
+        self.event.duration = Some(duration);
+        self
+    }
+
+    pub fn dt_stamp(mut self, dt_stamp: DateOrDateTime) -> Self {
+        self.event.dt_stamp = dt_stamp;
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.event.description = Some(description.into());
+        self
+    }
+
+    pub fn rrule(mut self, rrule: RRule) -> Self {
+        self.event.rrule = Some(rrule);
+        self
+    }
+
+    pub fn exdate(mut self, exdate: TzIdDateTime) -> Self {
+        self.event.exdates.push(exdate);
+        self
+    }
+
+    pub fn rdate(mut self, rdate: RDate) -> Self {
+        self.event.rdates.push(rdate);
+        self
+    }
+
+    pub fn sequence(mut self, sequence: u32) -> Self {
+        self.event.sequence = sequence;
+        self
+    }
+
+    pub fn priority(mut self, priority: u8) -> Self {
+        self.event.priority = Some(priority);
+        self
+    }
+
+    pub fn status(mut self, status: impl Into<String>) -> Self {
+        self.event.status = Some(status.into());
+        self
+    }
+
+    /// The calendar address of the organizer, e.g. `mailto:john@example.com`, optionally
+    /// preceded by parameters and `:`, e.g. `CN=John:mailto:john@example.com` (see
+    /// [`VEvent::organizer`]).
+    pub fn organizer(mut self, organizer: impl Into<String>) -> Self {
+        self.event.organizer = Some(organizer.into());
+        self
+    }
+
+    pub fn color(mut self, color: impl Into<String>) -> Self {
+        self.event.color = Some(color.into());
+        self
+    }
+
+    pub fn comment(mut self, comment: impl Into<String>) -> Self {
+        self.event.comments.push(comment.into());
+        self
+    }
+
+    pub fn attachment(mut self, attachment: Attachment) -> Self {
+        self.event.attachments.push(attachment);
+        self
+    }
+
+    /// Checks the event and returns it. The event must not end before it starts, and the
+    /// `UNTIL` of the recurrence rule must have the same value type (date, UTC date time or
+    /// floating date time) as `DTSTART` (RFC 5545, 3.3.10).
+    pub fn build(mut self) -> Result<VEvent, VEventBuildError> {
+        let dt_start = self.event.dt_start;
+
+        if let Some(duration) = self.event.duration {
+            self.event.dt_end = dt_start
+                .checked_add_signed(duration)
+                .ok_or(VEventBuildError::EndOutOfRange { dt_start, duration })?;
+        }
+        let dt_end = self.event.dt_end;
+        if dt_end < dt_start {
+            return Err(VEventBuildError::EndBeforeStart { dt_start, dt_end });
+        }
+
+        if let Some(until) = self
+            .event
+            .rrule
+            .as_ref()
+            .and_then(|rrule| rrule.common_options().until)
+        {
+            if std::mem::discriminant(&until) != std::mem::discriminant(&dt_start) {
+                return Err(VEventBuildError::UntilValueTypeMismatch { dt_start, until });
+            }
+        }
+
+        Ok(self.event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn until_value_type() {
+        let birthday =
+            DateOrDateTime::WholeDay(Utc.with_ymd_and_hms(2022, 7, 15, 0, 0, 0).unwrap());
+
+        let result = VEventBuilder::new(birthday, "Birthday")
+            .rrule("FREQ=YEARLY;UNTIL=20300715T000000Z".parse().unwrap())
+            .build();
+        assert!(matches!(
+            result,
+            Err(VEventBuildError::UntilValueTypeMismatch { .. })
+        ));
+
+        let event = VEventBuilder::new(birthday, "Birthday")
+            .uid("birthday")
+            .dt_end(birthday.succ_day())
+            .rrule("FREQ=YEARLY;UNTIL=20300715".parse().unwrap())
+            .build()
+            .unwrap();
+        assert_eq!(event.uid.as_deref(), Some("birthday"));
+        assert_eq!(event.into_iter().count(), 9);
+    }
+
+    #[test]
+    fn end_before_start() {
+        let start = DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, 6, 10, 0, 0).unwrap());
+
+        let result = VEventBuilder::new(start, "Backwards")
+            .dt_end(start - chrono::Duration::hours(1))
+            .build();
+        assert!(matches!(
+            result,
+            Err(VEventBuildError::EndBeforeStart { .. })
+        ));
+
+        let result = VEventBuilder::new(start, "Backwards")
+            .duration(chrono::Duration::hours(-1))
+            .build();
+        assert!(matches!(
+            result,
+            Err(VEventBuildError::EndBeforeStart { .. })
+        ));
+
+        let result = VEventBuilder::new(start, "Forever")
+            .duration(chrono::Duration::days(100_000_000))
+            .build();
+        assert!(matches!(
+            result,
+            Err(VEventBuildError::EndOutOfRange { .. })
+        ));
+
+        let event = VEventBuilder::new(start, "One hour")
+            .duration(chrono::Duration::hours(1))
+            .build()
+            .unwrap();
+        assert_eq!(event.dt_end, start + chrono::Duration::hours(1));
+        assert_eq!(event.duration, Some(chrono::Duration::hours(1)));
+    }
+
+    #[test]
+    fn organizer() {
+        for organizer in ["mailto:a@b.c", "CN=John:mailto:a@b.c"] {
+            let start =
+                DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, 6, 10, 0, 0).unwrap());
+            let event = VEventBuilder::new(start, "Meeting")
+                .uid("meeting")
+                .organizer(organizer)
+                .build()
+                .unwrap();
+
+            let block = crate::Block::from(&event);
+            let parsed = VEvent::try_from(block).unwrap();
+            assert_eq!(parsed.organizer.as_deref(), Some(organizer));
+        }
+    }
+}
@@ -1,160 +1,284 @@
 use std::{cmp::Ordering, ops::Range};
 
+use chrono::Duration;
+
 use crate::{
     date_or_date_time::DateOrDateTime,
     rrule::{Options, RRule},
-    VEvent,
+    RDate, VEvent,
 };
-use chrono::Duration;
 
 #[derive(Debug, Clone)]
 pub struct VEventIterator<'a> {
     event: &'a VEvent,
+    /// Start of the last occurrence, from the rule or from an RDATE.
     last_occurrence: Option<DateOrDateTime>,
+    /// End of the last occurrence if it is an RDATE period.
+    last_period_end: Option<DateOrDateTime>,
+    last_rule_occurrence: Option<DateOrDateTime>,
+    /// Occurrence of the rule already computed, waiting for earlier RDATEs.
+    next_rule_occurrence: Option<DateOrDateTime>,
+    /// Occurrences generated by the rule, DTSTART included, for COUNT.
+    rule_count: u32,
+    /// Starts (and ends, for periods) of the RDATEs, sorted and without duplicates.
+    rdates: Vec<(DateOrDateTime, Option<DateOrDateTime>)>,
+    next_rdate: usize,
     count: u32,
+    skip_cancelled: bool,
+    cutoff: Option<DateOrDateTime>,
+    /// Occurrences ending before it are skipped, until one doesn't.
+    from: Option<DateOrDateTime>,
 }
 
 impl<'a> VEventIterator<'a> {
     pub(crate) fn new(event: &'a VEvent) -> Self {
+        let mut rdates = event
+            .rdates
+            .iter()
+            .map(|rdate| match rdate {
+                RDate::DateTime(date_time) => (date_time.date_time, None),
+                RDate::Period { start, end } => (start.date_time, Some(end.date_time)),
+            })
+            .collect::<Vec<_>>();
+        // periods first, so that they win over a plain RDATE with the same start
+        rdates.sort_by_key(|(start, end)| (*start, end.is_none()));
+        rdates.dedup_by_key(|(start, _)| *start);
+
         Self {
             event,
             last_occurrence: None,
+            last_period_end: None,
+            last_rule_occurrence: None,
+            next_rule_occurrence: None,
+            rule_count: 0,
+            rdates,
+            next_rdate: 0,
             count: 0,
+            skip_cancelled: false,
+            cutoff: None,
+            from: None,
         }
     }
 
-    fn get_next_occurrence_according_to_rule(
-        &mut self,
-        last_occurrence: DateOrDateTime,
-        rrule: &RRule,
-    ) -> Option<DateOrDateTime> {
-        match rrule {
-            RRule::Yearly(_rrule) => {
-                let next_occurrence = last_occurrence.inc_year(1);
-                if !rrule.is_expired(next_occurrence) {
-                    self.last_occurrence = Some(next_occurrence);
-                    self.last_occurrence
-                } else {
-                    None
-                }
-            }
+    /// Whether to yield nothing for cancelled events (`STATUS:CANCELLED`). By default they
+    /// are yielded like any other event.
+    pub fn skip_cancelled(mut self, skip_cancelled: bool) -> Self {
+        self.skip_cancelled = skip_cancelled;
+        self
+    }
 
-            RRule::YearlyByMonthByDay(_rrule) => {
-                unimplemented!();
-            }
+    /// Stops at the first occurrence starting after `cutoff`, regardless of the UNTIL of the
+    /// recurrence rule. Useful to bound unbounded rules without changing the event.
+    pub fn until(mut self, cutoff: DateOrDateTime) -> Self {
+        self.cutoff = Some(cutoff);
+        self
+    }
 
-            RRule::YearlyByMonthByMonthDay(_rrule) => {
-                let next_occurrence = last_occurrence.inc_year(1);
-                if !rrule.is_expired(next_occurrence) {
-                    self.last_occurrence = Some(next_occurrence);
-                    self.last_occurrence
-                } else {
-                    None
-                }
-            }
+    /// Skips the occurrences that have not ended before `from`, see [`VEvent::iter_from`].
+    pub(crate) fn starting_from(mut self, from: DateOrDateTime) -> Self {
+        self.fast_forward(from);
+        self.from = Some(from);
+        self
+    }
 
-            RRule::MonthlyByMonthDay(rrule) => {
-                let next_occurrence =
-                    last_occurrence.inc_month(rrule.common_options().interval.unwrap_or(1));
+    fn is_past_cutoff(&self, occurrence: DateOrDateTime) -> bool {
+        self.cutoff.is_some_and(|cutoff| occurrence > cutoff)
+    }
 
-                if !rrule.is_expired(next_occurrence) {
-                    self.last_occurrence = Some(next_occurrence);
-                    self.last_occurrence
-                } else {
-                    None
-                }
-            }
+    /// Number of occurrences generated so far, including the ones removed by EXDATE.
+    pub fn count_so_far(&self) -> u32 {
+        self.count
+    }
 
-            RRule::MonthlyByDay(rrule) => {
-                let next_month = last_occurrence
-                    .substitute(
-                        Some(if last_occurrence.month() == 12 {
-                            last_occurrence.year() + 1
-                        } else {
-                            last_occurrence.year()
-                        }),
-                        Some(if last_occurrence.month() == 12 {
-                            1
-                        } else {
-                            last_occurrence.month() + 1
-                        }),
-                        Some(1),
-                        None,
-                        None,
-                        None,
-                    )
-                    .unwrap();
+    /// Skips, without generating them, occurrences that certainly end at least a day before
+    /// `from`. Only rules with a fixed step (see `fixed_step`) are fast forwarded, and only
+    /// before the first occurrence has been yielded; otherwise this does nothing.
+    pub(crate) fn fast_forward(&mut self, from: DateOrDateTime) {
+        if self.last_occurrence.is_some() {
+            return;
+        }
 
-                // Calculate 1SU or -1SU... done in DateOrDatetime
-                let next_occurrence = next_month.next_by_day(&rrule.day);
+        let Some(step) = self.fixed_step() else {
+            return;
+        };
+        let dt_start = self.event.dt_start;
+        let until = DateOrDateTime::DateTime(from.date() - self.event.duration());
 
-                if !rrule.is_expired(next_occurrence) {
-                    self.last_occurrence = Some(next_occurrence);
-                    self.last_occurrence
-                } else {
-                    None
-                }
+        let (gap, step) = match step {
+            FixedStep::Seconds(seconds) => {
+                ((until.date() - dt_start.date()).num_seconds(), seconds)
             }
+            FixedStep::Days(days) => ((until.date() - dt_start.date()).num_days(), days),
+            FixedStep::Months { months, .. } => (
+                (until.year() - dt_start.year()) as i64 * 12 + until.month() as i64
+                    - dt_start.month() as i64,
+                months as i64,
+            ),
+        };
+        // stop a step early so the occurrence ending right before `from` is not skipped
+        let skip = gap / step - 1;
+        if skip > 0 {
+            self.nth(skip as usize - 1);
+        }
+    }
 
-            RRule::Weekly(rrule) => {
-                let next_occurrence = last_occurrence + Duration::days(7);
+    /// Step between two consecutive occurrences, if it can be computed without generating
+    /// them. Rules whose step depends on the calendar (like months without the requested
+    /// day), and events with EXDATEs or RDATEs (which remove or add occurrences), return
+    /// `None`.
+    fn fixed_step(&self) -> Option<FixedStep> {
+        if !self.event.exdates.is_empty() || !self.event.rdates.is_empty() {
+            return None;
+        }
 
-                if !rrule.is_expired(next_occurrence) {
-                    self.last_occurrence = Some(next_occurrence);
-                    self.last_occurrence
-                } else {
-                    None
-                }
+        let rrule = self.event.rrule.as_ref()?;
+        let interval = rrule.common_options().interval.unwrap_or(1);
+        if interval == 0 {
+            return None;
+        }
+        let dt_start = self.event.dt_start;
+        match rrule {
+            RRule::Secondly(rrule)
+                if rrule.by_second.is_empty()
+                    && !matches!(dt_start, DateOrDateTime::WholeDay(_)) =>
+            {
+                Some(FixedStep::Seconds(interval as i64))
             }
+            RRule::Daily(_) => Some(FixedStep::Days(interval as i64)),
+            RRule::Weekly(_) => Some(FixedStep::Days(7 * interval as i64)),
+            RRule::MonthlyByMonthDay(rrule) if rrule.month_day <= 28 => Some(FixedStep::Months {
+                months: interval,
+                day: rrule.month_day as u32,
+            }),
+            // every year has the day, but February 29th
+            RRule::Yearly(_) | RRule::YearlyByMonthByMonthDay(_)
+                if (dt_start.month(), dt_start.day()) != (2, 29) =>
+            {
+                Some(FixedStep::Months {
+                    months: interval.checked_mul(12)?,
+                    day: dt_start.day(),
+                })
+            }
+            _ => None,
+        }
+    }
 
-            RRule::WeeklyByDay(rrule) => {
-                let next_occurrence = last_occurrence.next_by_day(&rrule.day);
-                log::debug!(
-                    "last_occurrence == {:?}, next_occurrence == {:?}",
-                    last_occurrence,
-                    next_occurrence
-                );
+    /// Number of occurrences up to `UNTIL` of a rule with a fixed step (see `fixed_step`),
+    /// found by bisecting on the number of steps instead of generating them. `COUNT` is not
+    /// taken into account. `None` for other rules.
+    pub(crate) fn count_until(&self) -> Option<usize> {
+        let step = self.fixed_step()?;
+        let rrule = self.event.rrule.as_ref()?;
+        rrule.common_options().until?;
 
-                if !rrule.is_expired(next_occurrence) {
-                    self.last_occurrence = Some(next_occurrence);
-                    self.last_occurrence
-                } else {
-                    None
-                }
+        let dt_start = self.event.dt_start;
+        let within_until = |steps| {
+            step.advance(dt_start, steps)
+                .is_some_and(|occurrence| !rrule.is_expired(occurrence))
+        };
+
+        // DTSTART is always the first occurrence, more than 2^40 steps are out of range
+        let (mut low, mut high) = (0, 1 << 40);
+        while high - low > 1 {
+            let mid = low + (high - low) / 2;
+            if within_until(mid) {
+                low = mid;
+            } else {
+                high = mid;
             }
+        }
+        usize::try_from(low + 1).ok()
+    }
 
-            RRule::Daily(rrule) => {
-                let next_occurrence = last_occurrence + Duration::days(1);
+    /// Next occurrence of the rule merged with the RDATEs. An RDATE falling on an occurrence
+    /// already generated (or before DTSTART) is skipped.
+    fn get_next_occurrence_according_to_rule_and_iterations(&mut self) -> Option<DateOrDateTime> {
+        let Some(last_occurrence) = self.last_occurrence else {
+            // DTSTART is always the first occurrence, and the first of the rule too
+            let dt_start = self.event.dt_start;
+            self.last_occurrence = Some(dt_start);
+            self.last_rule_occurrence = Some(dt_start);
+            self.rule_count = 1;
+            self.skip_rdates(|start| start < dt_start);
+            self.last_period_end = self.period_end(dt_start);
+            return Some(dt_start);
+        };
 
-                if !rrule.is_expired(next_occurrence) {
-                    self.last_occurrence = Some(next_occurrence);
-                    self.last_occurrence
-                } else {
-                    None
+        if self.next_rule_occurrence.is_none() {
+            if let (Some(rrule), Some(last_rule_occurrence)) =
+                (&self.event.rrule, self.last_rule_occurrence)
+            {
+                if !rrule.is_out_of_count(self.rule_count) {
+                    self.next_rule_occurrence = rrule.next_occurrence(last_rule_occurrence);
                 }
             }
         }
+
+        self.skip_rdates(|start| start <= last_occurrence);
+        let next_rdate = self.rdates.get(self.next_rdate).map(|(start, _)| *start);
+
+        let next_occurrence = match (self.next_rule_occurrence, next_rdate) {
+            (Some(rule_occurrence), next_rdate)
+                if next_rdate.is_none_or(|next_rdate| rule_occurrence <= next_rdate) =>
+            {
+                self.next_rule_occurrence = None;
+                self.last_rule_occurrence = Some(rule_occurrence);
+                self.rule_count += 1;
+                rule_occurrence
+            }
+            (_, next_rdate) => next_rdate?,
+        };
+        self.last_occurrence = Some(next_occurrence);
+        self.last_period_end = self.period_end(next_occurrence);
+        Some(next_occurrence)
     }
 
-    fn get_next_occurrence_according_to_rule_and_iterations(&mut self) -> Option<DateOrDateTime> {
-        if let Some(last_occurrence) = self.last_occurrence {
-            self.event.rrule.as_ref().and_then(|rrule| {
-                if rrule.is_out_of_count(self.count) {
-                    return None;
-                }
-                let mut next_occurrence = Some(last_occurrence);
-                let mut iterations = rrule.common_options().interval.unwrap_or(1);
-                while iterations > 0 && next_occurrence.is_some() {
-                    next_occurrence =
-                        self.get_next_occurrence_according_to_rule(next_occurrence.unwrap(), rrule);
-                    iterations -= 1;
-                }
+    fn skip_rdates(&mut self, skip: impl Fn(DateOrDateTime) -> bool) {
+        while self
+            .rdates
+            .get(self.next_rdate)
+            .is_some_and(|(start, _)| skip(*start))
+        {
+            self.next_rdate += 1;
+        }
+    }
 
-                next_occurrence
-            })
-        } else {
-            self.last_occurrence = Some(self.event.dt_start);
-            Some(self.event.dt_start)
+    /// End of the RDATE period starting at `start`, if any.
+    fn period_end(&self, start: DateOrDateTime) -> Option<DateOrDateTime> {
+        self.rdates
+            .get(self.next_rdate)
+            .filter(|(rdate_start, _)| *rdate_start == start)
+            .and_then(|(_, end)| *end)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum FixedStep {
+    Seconds(i64),
+    Days(i64),
+    /// On `day` every `months` months.
+    Months {
+        months: u32,
+        day: u32,
+    },
+}
+
+impl FixedStep {
+    /// The occurrence `steps` steps after `occurrence`, `None` if out of range.
+    fn advance(self, occurrence: DateOrDateTime, steps: i64) -> Option<DateOrDateTime> {
+        if steps == 0 {
+            return Some(occurrence);
+        }
+
+        match self {
+            FixedStep::Seconds(seconds) => occurrence.checked_add_signed(Duration::milliseconds(
+                steps.checked_mul(seconds)?.checked_mul(1000)?,
+            )),
+            FixedStep::Days(days) => occurrence.checked_add_days(steps.checked_mul(days)?),
+            FixedStep::Months { months, day } => {
+                let months = u32::try_from(steps).ok()?.checked_mul(months)?;
+                occurrence.with_day(1)?.inc_month(months)?.with_day(day)
+            }
         }
     }
 }
@@ -162,9 +286,63 @@ impl<'a> VEventIterator<'a> {
 impl<'a> Iterator for VEventIterator<'a> {
     type Item = Range<DateOrDateTime>;
 
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        // the occurrences skipped until `from` don't count
+        if n > 0 && self.from.is_none() {
+            if let Some(step) = self.fixed_step() {
+                // Put the iterator in the state it would have after yielding n more
+                // occurrences, then let next() handle COUNT and UNTIL as usual.
+                let last_occurrence = match self.last_occurrence {
+                    Some(last_occurrence) => step.advance(last_occurrence, n as i64),
+                    None => step.advance(self.event.dt_start, n as i64 - 1),
+                }?;
+
+                // there are no RDATEs, all the occurrences come from the rule
+                self.last_occurrence = Some(last_occurrence);
+                self.last_rule_occurrence = Some(last_occurrence);
+                self.count = self
+                    .count
+                    .saturating_add(u32::try_from(n).unwrap_or(u32::MAX));
+                self.rule_count = self.count;
+                return self.next();
+            }
+        }
+
+        for _ in 0..n {
+            self.next()?;
+        }
+        self.next()
+    }
+
     fn next(&mut self) -> Option<Self::Item> {
+        let Some(from) = self.from else {
+            return self.next_occurrence();
+        };
+
+        loop {
+            let occurrence = self.next_occurrence()?;
+            if occurrence.start >= from || occurrence.end > from {
+                self.from = None;
+                return Some(occurrence);
+            }
+        }
+    }
+}
+
+impl<'a> VEventIterator<'a> {
+    fn next_occurrence(&mut self) -> Option<Range<DateOrDateTime>> {
         log::trace!("function next({:?}) called", self);
 
+        if self.skip_cancelled && self.event.is_cancelled() {
+            return None;
+        }
+        if self
+            .last_occurrence
+            .is_some_and(|last_occurrence| self.is_past_cutoff(last_occurrence))
+        {
+            return None;
+        }
+
         let mut next = self.get_next_occurrence_according_to_rule_and_iterations();
         log::trace!("next == {:?}", next);
 
@@ -172,6 +350,13 @@ impl<'a> Iterator for VEventIterator<'a> {
             // remove dates appearing in ExDate field
             if let Some(next_non_empty) = next {
                 log::trace!("next_non_empty == {:?}", next_non_empty);
+                if self.is_past_cutoff(next_non_empty) {
+                    return None;
+                }
+
+                // COUNT applies to the occurrences generated by the rule, before EXDATEs
+                // remove some of them
+                self.count += 1;
 
                 if !self.event.exdates.iter().any(|exdate| {
                     // we check only for date comparison and not time because of the weird handling
@@ -179,12 +364,11 @@ impl<'a> Iterator for VEventIterator<'a> {
                     // most per day.
                     next_non_empty.date().cmp(&exdate.date_time.date()) == Ordering::Equal
                 }) {
-                    // keep count
-                    self.count += 1;
-
-                    // calculate how long it's supposed to last
-                    let delta = self.event.dt_end - self.event.dt_start;
-                    let next_non_empty_end = next_non_empty + delta;
+                    // calculate how long it's supposed to last: RDATE periods have their own
+                    // length
+                    let next_non_empty_end = self
+                        .last_period_end
+                        .unwrap_or_else(|| next_non_empty + self.event.duration());
                     return Some(Range {
                         start: next_non_empty,
                         end: next_non_empty_end,
@@ -198,3 +382,651 @@ impl<'a> Iterator for VEventIterator<'a> {
         }
     }
 }
+
+/// The occurrences of an event with a finite number of them, which can also be walked
+/// backward from the last one. See [`VEvent::bounded_occurrences`].
+#[derive(Debug, Clone)]
+pub struct BoundedOccurrences {
+    occurrences: std::vec::IntoIter<Range<DateOrDateTime>>,
+}
+
+impl BoundedOccurrences {
+    /// Generates all the occurrences of `event` upfront: going backward needs the last one,
+    /// which can only be found by going forward.
+    pub(crate) fn new(event: &VEvent) -> Self {
+        Self {
+            occurrences: event.into_iter().collect::<Vec<_>>().into_iter(),
+        }
+    }
+}
+
+impl Iterator for BoundedOccurrences {
+    type Item = Range<DateOrDateTime>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.occurrences.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.occurrences.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for BoundedOccurrences {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.occurrences.next_back()
+    }
+}
+
+impl ExactSizeIterator for BoundedOccurrences {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{vevent::tests::parse_event, DateOrDateTime};
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn nth_matches_next() {
+        for rrule in [
+            "RRULE:FREQ=DAILY",
+            "RRULE:FREQ=DAILY;INTERVAL=3",
+            "RRULE:FREQ=WEEKLY;INTERVAL=2",
+            "RRULE:FREQ=DAILY;COUNT=10",
+            "RRULE:FREQ=DAILY;UNTIL=20220110T000000Z",
+            "RRULE:FREQ=MONTHLY;BYMONTHDAY=15;INTERVAL=5",
+            "RRULE:FREQ=MONTHLY;BYMONTHDAY=3;COUNT=20",
+            "RRULE:FREQ=YEARLY;INTERVAL=2",
+        ] {
+            let event = parse_event(&["DTSTART:20220103T100000Z", rrule]);
+
+            for n in [0, 1, 5, 9, 10, 11, 1000] {
+                let mut stepwise = event.into_iter();
+                for _ in 0..n {
+                    stepwise.next();
+                }
+                assert_eq!(
+                    event.into_iter().nth(n),
+                    stepwise.next(),
+                    "{rrule} nth({n})"
+                );
+            }
+
+            let mut iter = event.into_iter();
+            iter.next();
+            let mut stepwise = iter.clone();
+            stepwise.next();
+            stepwise.next();
+            assert_eq!(iter.nth(2), stepwise.next(), "{rrule} nth(2) after next()");
+        }
+    }
+
+    #[test]
+    fn count_until_matches_next() {
+        for rrule in [
+            "RRULE:FREQ=DAILY;UNTIL=20220110T100000Z",
+            "RRULE:FREQ=DAILY;UNTIL=20220110T095959Z",
+            "RRULE:FREQ=DAILY;INTERVAL=3;UNTIL=20220301",
+            "RRULE:FREQ=WEEKLY;INTERVAL=2;UNTIL=20230101T000000Z",
+            "RRULE:FREQ=MONTHLY;BYMONTHDAY=15;UNTIL=20250115T100000Z",
+            "RRULE:FREQ=YEARLY;INTERVAL=2;UNTIL=20400103",
+            "RRULE:FREQ=DAILY;UNTIL=20220101T000000Z",
+        ] {
+            let event = parse_event(&["DTSTART:20220103T100000Z", rrule]);
+            assert_eq!(
+                event.into_iter().count_until(),
+                Some(event.into_iter().count()),
+                "{rrule}"
+            );
+        }
+    }
+
+    #[test]
+    fn nth_huge_interval() {
+        for rrule in [
+            "RRULE:FREQ=DAILY;INTERVAL=4294967295",
+            "RRULE:FREQ=WEEKLY;INTERVAL=4294967295",
+            "RRULE:FREQ=MONTHLY;BYMONTHDAY=6;INTERVAL=4294967295",
+            "RRULE:FREQ=YEARLY;INTERVAL=4294967295",
+        ] {
+            let event = parse_event(&["DTSTART:20220106T100000Z", rrule]);
+            assert_eq!(event.into_iter().nth(1), None, "{rrule}");
+            assert_eq!(event.into_iter().count(), 1, "{rrule}");
+        }
+    }
+
+    #[test]
+    fn count_so_far() {
+        let event = parse_event(&["DTSTART:20220103T100000Z", "RRULE:FREQ=DAILY"]);
+        let mut iter = event.into_iter();
+        assert_eq!(iter.count_so_far(), 0);
+
+        for n in 1..=5 {
+            iter.next().unwrap();
+            assert_eq!(iter.count_so_far(), n);
+        }
+
+        iter.nth(9).unwrap();
+        assert_eq!(iter.count_so_far(), 15);
+    }
+
+    #[test]
+    fn until_and_count() {
+        let day = |d| DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, d, 10, 0, 0).unwrap());
+
+        // UNTIL reached first
+        let event = parse_event(&[
+            "DTSTART:20220103T100000Z",
+            "RRULE:FREQ=DAILY;COUNT=10;UNTIL=20220107T100000Z",
+        ]);
+        assert_eq!(
+            event.into_iter().map(|o| o.start).collect::<Vec<_>>(),
+            [day(3), day(4), day(5), day(6), day(7)]
+        );
+
+        // COUNT reached first
+        let event = parse_event(&[
+            "DTSTART:20220103T100000Z",
+            "RRULE:FREQ=DAILY;COUNT=3;UNTIL=20220131T100000Z",
+        ]);
+        assert_eq!(
+            event.into_iter().map(|o| o.start).collect::<Vec<_>>(),
+            [day(3), day(4), day(5)]
+        );
+
+        // with INTERVAL
+        let event = parse_event(&[
+            "DTSTART:20220103T100000Z",
+            "RRULE:FREQ=DAILY;INTERVAL=2;COUNT=10;UNTIL=20220108T100000Z",
+        ]);
+        assert_eq!(
+            event.into_iter().map(|o| o.start).collect::<Vec<_>>(),
+            [day(3), day(5), day(7)]
+        );
+    }
+
+    #[test]
+    fn bounded_occurrences() {
+        let day = |d| DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, d, 10, 0, 0).unwrap());
+
+        let event = parse_event(&[
+            "DTSTART:20220103T100000Z",
+            "RRULE:FREQ=DAILY;UNTIL=20220110T100000Z",
+            "EXDATE:20220109T100000Z",
+        ]);
+        let occurrences = event.bounded_occurrences().unwrap();
+        assert_eq!(occurrences.len(), 7);
+        assert_eq!(
+            occurrences
+                .rev()
+                .map(|o| o.start)
+                .take(3)
+                .collect::<Vec<_>>(),
+            [day(10), day(8), day(7)]
+        );
+
+        // the most recent past occurrence
+        let now = day(5) + chrono::Duration::hours(3);
+        assert_eq!(
+            event
+                .bounded_occurrences()
+                .unwrap()
+                .rev()
+                .find(|o| o.start <= now)
+                .map(|o| o.start),
+            Some(day(5))
+        );
+
+        let mut occurrences = event.bounded_occurrences().unwrap();
+        assert_eq!(occurrences.next().map(|o| o.start), Some(day(3)));
+        assert_eq!(occurrences.next_back().map(|o| o.start), Some(day(10)));
+        assert_eq!(occurrences.len(), 5);
+
+        let event = parse_event(&["DTSTART:20220103T100000Z", "RRULE:FREQ=DAILY"]);
+        assert!(event.bounded_occurrences().is_none());
+
+        // too many to generate upfront
+        for rrule in [
+            "RRULE:FREQ=SECONDLY;COUNT=4294967295",
+            "RRULE:FREQ=DAILY;UNTIL=99991231T235959Z",
+        ] {
+            let event = parse_event(&["DTSTART:20220103T100000Z", rrule]);
+            assert!(event.bounded_occurrences().is_none(), "{rrule}");
+        }
+    }
+
+    #[test]
+    fn count_includes_dtstart() {
+        let day = |d| DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, d, 10, 0, 0).unwrap());
+
+        for (count, expected) in [
+            (1, &[day(3)][..]),
+            (2, &[day(3), day(4)]),
+            (3, &[day(3), day(4), day(5)]),
+        ] {
+            let rrule = format!("RRULE:FREQ=DAILY;COUNT={count}");
+            let event = parse_event(&["DTSTART:20220103T100000Z", &rrule]);
+            assert_eq!(
+                event.into_iter().map(|o| o.start).collect::<Vec<_>>(),
+                expected,
+                "{rrule}"
+            );
+            assert_eq!(event.occurrence_count(), Some(count), "{rrule}");
+        }
+    }
+
+    #[test]
+    fn until_inclusive() {
+        let at =
+            |d, h| DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, d, h, 0, 0).unwrap());
+        let day = |d| DateOrDateTime::WholeDay(Utc.with_ymd_and_hms(2022, 1, d, 0, 0, 0).unwrap());
+        let starts = |lines: &[&str]| {
+            parse_event(lines)
+                .into_iter()
+                .map(|o| o.start)
+                .collect::<Vec<_>>()
+        };
+
+        // timed, UNTIL exactly on the last occurrence
+        assert_eq!(
+            starts(&[
+                "DTSTART:20220103T100000Z",
+                "RRULE:FREQ=DAILY;UNTIL=20220105T100000Z",
+            ]),
+            [at(3, 10), at(4, 10), at(5, 10)]
+        );
+        // timed starting at midnight, UNTIL exactly on the last occurrence
+        assert_eq!(
+            starts(&[
+                "DTSTART:20220103T000000Z",
+                "RRULE:FREQ=DAILY;UNTIL=20220105T000000Z",
+            ]),
+            [at(3, 0), at(4, 0), at(5, 0)]
+        );
+        // timed with a DATE UNTIL: the whole day is included
+        assert_eq!(
+            starts(&[
+                "DTSTART:20220103T100000Z",
+                "RRULE:FREQ=DAILY;UNTIL=20220105"
+            ]),
+            [at(3, 10), at(4, 10), at(5, 10)]
+        );
+        // whole day with a DATE UNTIL
+        assert_eq!(
+            starts(&[
+                "DTSTART;VALUE=DATE:20220103",
+                "RRULE:FREQ=DAILY;UNTIL=20220105"
+            ]),
+            [day(3), day(4), day(5)]
+        );
+        // whole day with a midnight DATE-TIME UNTIL
+        assert_eq!(
+            starts(&[
+                "DTSTART;VALUE=DATE:20220103",
+                "RRULE:FREQ=DAILY;UNTIL=20220105T000000Z",
+            ]),
+            [day(3), day(4), day(5)]
+        );
+    }
+
+    #[test]
+    fn count_includes_exdates() {
+        let day = |d| DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, d, 10, 0, 0).unwrap());
+
+        let event = parse_event(&[
+            "DTSTART:20220103T100000Z",
+            "RRULE:FREQ=DAILY;COUNT=4",
+            "EXDATE;TZID=Europe/Rome:20220104T110000",
+        ]);
+        assert_eq!(
+            event.into_iter().map(|o| o.start).collect::<Vec<_>>(),
+            [day(3), day(5), day(6)]
+        );
+    }
+
+    #[test]
+    fn monthly_by_month_day_31() {
+        let event = parse_event(&[
+            "DTSTART:20220131T100000Z",
+            "RRULE:FREQ=MONTHLY;BYMONTHDAY=31;UNTIL=20221231T235959Z",
+        ]);
+
+        let occurrences = event
+            .into_iter()
+            .map(|o| (o.start.month(), o.start.day()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            occurrences,
+            [
+                (1, 31),
+                (3, 31),
+                (5, 31),
+                (7, 31),
+                (8, 31),
+                (10, 31),
+                (12, 31)
+            ]
+        );
+
+        let event = parse_event(&[
+            "DTSTART:20220130T100000Z",
+            "RRULE:FREQ=MONTHLY;BYMONTHDAY=30;COUNT=3",
+        ]);
+        let occurrences = event
+            .into_iter()
+            .map(|o| (o.start.month(), o.start.day()))
+            .collect::<Vec<_>>();
+        assert_eq!(occurrences, [(1, 30), (3, 30), (4, 30)]);
+    }
+
+    #[test]
+    fn monthly_fifth_monday() {
+        let day =
+            |m, d| DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, m, d, 10, 0, 0).unwrap());
+
+        let event = parse_event(&[
+            "DTSTART:20220131T100000Z",
+            "RRULE:FREQ=MONTHLY;BYDAY=5MO;COUNT=4",
+        ]);
+        assert_eq!(
+            event.into_iter().map(|o| o.start).collect::<Vec<_>>(),
+            [day(1, 31), day(5, 30), day(8, 29), day(10, 31)]
+        );
+
+        // a 6th Friday never exists: the iteration ends instead of looping forever
+        let event = parse_event(&["DTSTART:20220107T100000Z", "RRULE:FREQ=MONTHLY;BYDAY=6FR"]);
+        assert_eq!(event.into_iter().count(), 1);
+    }
+
+    #[test]
+    fn secondly_by_second() {
+        let event = parse_event(&[
+            "DTSTART:20220106T100000Z",
+            "RRULE:FREQ=SECONDLY;BYSECOND=0,30;COUNT=4",
+        ]);
+        let at =
+            |m, s| DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, 6, 10, m, s).unwrap());
+
+        assert_eq!(
+            event.into_iter().map(|o| o.start).collect::<Vec<_>>(),
+            [at(0, 0), at(0, 30), at(1, 0), at(1, 30)]
+        );
+    }
+
+    #[test]
+    fn nth_does_not_step() {
+        // stepping through 50M occurrences would take ages
+        let event = parse_event(&["DTSTART:20220103T100000Z", "RRULE:FREQ=DAILY"]);
+
+        let occurrence = event.into_iter().nth(50_000_000).unwrap();
+        assert_eq!(
+            occurrence.start,
+            DateOrDateTime::DateTime(
+                Utc.with_ymd_and_hms(2022, 1, 3, 10, 0, 0).unwrap()
+                    + chrono::Duration::days(50_000_000)
+            )
+        );
+    }
+
+    #[test]
+    fn yearly_all_day_birthday() {
+        // both spellings of an all-day DTSTART, anchored at UTC midnight so the
+        // host timezone cannot shift the day
+        for dt_start in ["DTSTART;VALUE=DATE:19900715", "DTSTART:19900715"] {
+            let event = parse_event(&[
+                dt_start,
+                "DTEND;VALUE=DATE:19900716",
+                "RRULE:FREQ=YEARLY;COUNT=80",
+            ]);
+
+            let occurrences = event.into_iter().map(|o| o.start).collect::<Vec<_>>();
+            assert_eq!(occurrences.len(), 80);
+            for (years, occurrence) in occurrences.into_iter().enumerate() {
+                assert_eq!(
+                    occurrence,
+                    DateOrDateTime::WholeDay(
+                        Utc.with_ymd_and_hms(1990 + years as i32, 7, 15, 0, 0, 0)
+                            .unwrap()
+                    )
+                );
+            }
+        }
+
+        // a leap day birthday only recurs in leap years
+        let event = parse_event(&["DTSTART;VALUE=DATE:20000229", "RRULE:FREQ=YEARLY;COUNT=3"]);
+        assert_eq!(
+            event
+                .into_iter()
+                .map(|o| (o.start.year(), o.start.month(), o.start.day()))
+                .collect::<Vec<_>>(),
+            [(2000, 2, 29), (2004, 2, 29), (2008, 2, 29)]
+        );
+    }
+
+    #[test]
+    fn monthly_by_weekdays_count() {
+        let day =
+            |m, d| DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, m, d, 10, 0, 0).unwrap());
+
+        let event = parse_event(&[
+            "DTSTART:20220124T100000Z",
+            "RRULE:FREQ=MONTHLY;BYDAY=MO,WE;COUNT=5",
+        ]);
+        assert_eq!(
+            event.into_iter().map(|o| o.start).collect::<Vec<_>>(),
+            [day(1, 24), day(1, 26), day(1, 31), day(2, 2), day(2, 7)]
+        );
+    }
+
+    #[test]
+    fn skip_cancelled() {
+        let event = parse_event(&[
+            "DTSTART:20220101T100000Z",
+            "RRULE:FREQ=DAILY;COUNT=3",
+            "STATUS:CANCELLED",
+        ]);
+        assert_eq!(event.into_iter().count(), 3);
+        assert_eq!(event.into_iter().skip_cancelled(true).count(), 0);
+
+        let event = parse_event(&[
+            "DTSTART:20220101T100000Z",
+            "RRULE:FREQ=DAILY;COUNT=3",
+            "STATUS:CONFIRMED",
+        ]);
+        assert_eq!(event.into_iter().skip_cancelled(true).count(), 3);
+    }
+
+    #[test]
+    fn until_cutoff() {
+        let day = |d| DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, d, 10, 0, 0).unwrap());
+        let event = parse_event(&["DTSTART:20220101T100000Z", "RRULE:FREQ=DAILY"]);
+
+        let mut iter = event.into_iter().until(day(5));
+        assert_eq!(
+            iter.by_ref().map(|o| o.start).collect::<Vec<_>>(),
+            (1..=5).map(day).collect::<Vec<_>>()
+        );
+        assert_eq!(iter.next(), None);
+
+        // the earliest of the cutoff and the rule UNTIL wins
+        let event = parse_event(&[
+            "DTSTART:20220101T100000Z",
+            "RRULE:FREQ=DAILY;UNTIL=20220103T100000Z",
+        ]);
+        assert_eq!(event.into_iter().until(day(5)).count(), 3);
+        assert_eq!(event.into_iter().until(day(1)).count(), 1);
+    }
+
+    #[test]
+    fn rdates_without_rrule() {
+        let at =
+            |d, h| DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, d, h, 0, 0).unwrap());
+        let event = parse_event(&[
+            "DTSTART:20220101T100000Z",
+            "DTEND:20220101T110000Z",
+            "RDATE;VALUE=PERIOD:20220110T090000Z/PT3H",
+            "RDATE:20220105T100000Z",
+        ]);
+
+        assert_eq!(
+            event.into_iter().collect::<Vec<_>>(),
+            [
+                at(1, 10)..at(1, 11),
+                at(5, 10)..at(5, 11),
+                at(10, 9)..at(10, 12)
+            ]
+        );
+
+        let event = parse_event(&[
+            "DTSTART:20220101T100000Z",
+            "RDATE:20220105T100000Z,20220101T100000Z,20220107T100000Z",
+            "EXDATE:20220107T100000Z",
+        ]);
+        assert_eq!(
+            event.into_iter().map(|o| o.start).collect::<Vec<_>>(),
+            [at(1, 10), at(5, 10)]
+        );
+    }
+
+    #[test]
+    fn rdates_with_rrule() {
+        let at =
+            |d, h| DateOrDateTime::DateTime(Utc.with_ymd_and_hms(2022, 1, d, h, 0, 0).unwrap());
+        let event = parse_event(&[
+            "DTSTART:20220103T100000Z",
+            "DTEND:20220103T110000Z",
+            "RRULE:FREQ=WEEKLY;COUNT=3",
+            // before DTSTART, on an occurrence of the rule, repeated, after the rule is over
+            "RDATE:20220101T100000Z,20220110T100000Z,20220105T100000Z",
+            "RDATE;VALUE=PERIOD:20220117T100000Z/PT3H,20220105T100000Z/PT2H",
+            "RDATE:20220131T100000Z",
+        ]);
+
+        assert_eq!(
+            event.into_iter().collect::<Vec<_>>(),
+            [
+                at(3, 10)..at(3, 11),
+                at(5, 10)..at(5, 12),
+                at(10, 10)..at(10, 11),
+                at(17, 10)..at(17, 13),
+                at(31, 10)..at(31, 11),
+            ]
+        );
+        assert_eq!(event.occurrence_count(), Some(5));
+        assert_eq!(
+            event
+                .bounded_occurrences()
+                .unwrap()
+                .next_back()
+                .map(|o| o.start),
+            Some(at(31, 10))
+        );
+    }
+
+    #[test]
+    fn generic_rule_yields_dtstart_only() {
+        let event = parse_event(&[
+            "DTSTART:20220131T100000Z",
+            "RRULE:FREQ=MONTHLY;BYSETPOS=-1;COUNT=3",
+        ]);
+
+        assert_eq!(
+            event.into_iter().map(|o| o.start).collect::<Vec<_>>(),
+            [event.dt_start]
+        );
+    }
+
+    #[test]
+    fn yearly_interval() {
+        let years = |lines: &[&str]| {
+            parse_event(lines)
+                .into_iter()
+                .map(|o| (o.start.year(), o.start.month(), o.start.day()))
+                .collect::<Vec<_>>()
+        };
+
+        // a leap day event, every four years
+        assert_eq!(
+            years(&[
+                "DTSTART;VALUE=DATE:20000229",
+                "RRULE:FREQ=YEARLY;INTERVAL=4;COUNT=4"
+            ]),
+            [(2000, 2, 29), (2004, 2, 29), (2008, 2, 29), (2012, 2, 29)]
+        );
+        assert_eq!(
+            years(&[
+                "DTSTART:20220701T100000Z",
+                "RRULE:FREQ=YEARLY;BYMONTH=7;BYMONTHDAY=1;INTERVAL=4;COUNT=3"
+            ]),
+            [(2022, 7, 1), (2026, 7, 1), (2030, 7, 1)]
+        );
+    }
+
+    #[test]
+    fn interval_spacing() {
+        let dates = |lines: &[&str]| {
+            parse_event(lines)
+                .into_iter()
+                .map(|o| (o.start.month(), o.start.day()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            dates(&[
+                "DTSTART:20220101T100000Z",
+                "RRULE:FREQ=DAILY;INTERVAL=3;COUNT=4"
+            ]),
+            [(1, 1), (1, 4), (1, 7), (1, 10)]
+        );
+        assert_eq!(
+            dates(&[
+                "DTSTART:20220103T100000Z",
+                "RRULE:FREQ=WEEKLY;INTERVAL=2;COUNT=3"
+            ]),
+            [(1, 3), (1, 17), (1, 31)]
+        );
+        assert_eq!(
+            dates(&[
+                "DTSTART:20220103T100000Z",
+                "RRULE:FREQ=WEEKLY;BYDAY=MO,WE;INTERVAL=2;COUNT=5"
+            ]),
+            [(1, 3), (1, 5), (1, 17), (1, 19), (1, 31)]
+        );
+        assert_eq!(
+            dates(&[
+                "DTSTART:20220115T100000Z",
+                "RRULE:FREQ=MONTHLY;BYMONTHDAY=15;INTERVAL=2;COUNT=3"
+            ]),
+            [(1, 15), (3, 15), (5, 15)]
+        );
+        assert_eq!(
+            dates(&[
+                "DTSTART:20220103T100000Z",
+                "RRULE:FREQ=MONTHLY;BYDAY=1MO;INTERVAL=3;COUNT=3"
+            ]),
+            [(1, 3), (4, 4), (7, 4)]
+        );
+        assert_eq!(
+            dates(&[
+                "DTSTART:20220124T100000Z",
+                "RRULE:FREQ=MONTHLY;BYDAY=MO;INTERVAL=2;COUNT=7"
+            ]),
+            [(1, 24), (1, 31), (3, 7), (3, 14), (3, 21), (3, 28), (5, 2)]
+        );
+    }
+
+    #[test]
+    fn yearly_by_month_by_day() {
+        let event = parse_event(&[
+            "DTSTART:20220327T010000Z",
+            "RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU;COUNT=3",
+        ]);
+
+        assert_eq!(
+            event
+                .into_iter()
+                .map(|o| (o.start.year(), o.start.month(), o.start.day()))
+                .collect::<Vec<_>>(),
+            [(2022, 3, 27), (2023, 3, 26), (2024, 3, 31)]
+        );
+    }
+}
@@ -1,8 +1,13 @@
-use crate::{block::Block, rrule::RRule};
-use chrono::NaiveDate;
+use crate::{
+    block::Block,
+    by_day::ByDay,
+    rrule::{Options, RRule, RRuleParseError},
+    DateOrDateTime,
+};
+use chrono::{Datelike, FixedOffset, NaiveDate, NaiveDateTime};
 use thiserror::Error;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct VTimezone {
     pub tz_id: String,
     pub offsets: Vec<VTimezoneOffset>, // TODO: populate!
@@ -24,17 +29,65 @@ pub enum VTimezoneOffsetParseError {
     MissingMandatoryField { block: Block, field: &'static str },
     #[error("Unsupported tag {tag:?}, Block: {block:?}")]
     UnsupportedTag { block: Block, tag: String },
+    #[error("DTSTART parse error")]
+    ChronoParseError(#[from] chrono::ParseError),
+    #[error("RRULE parse error")]
+    RRuleParseError(#[from] RRuleParseError),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Error, Debug)]
+pub enum VTimezoneResolveError {
+    #[error("The timezone has no STANDARD or DAYLIGHT observances")]
+    NoObservances,
+    #[error("UTC offset parse error")]
+    UtcOffsetParseError(#[from] UtcOffsetParseError),
+}
+
+#[derive(Error, Debug)]
+pub enum UtcOffsetParseError {
+    #[error("Invalid UTC offset {value:?}, expected ±HHMM or ±HHMMSS")]
+    InvalidFormat { value: String },
+    #[error("UTC offset {value:?} out of range")]
+    OutOfRange { value: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct VTimezoneOffset {
     pub tz_name: String,
     pub tz_offset_from: String,
     pub tz_offset_to: String,
-    pub dt_start: NaiveDate,
+    pub dt_start: NaiveDateTime,
     pub rrule: Option<RRule>,
 }
 
+impl VTimezone {
+    /// Offset in effect at the wall clock time `naive`, according to the STANDARD and DAYLIGHT
+    /// observances of the timezone and their recurrence rules. This allows interpreting
+    /// TZID-qualified times with the definitions embedded in the calendar.
+    ///
+    /// Transitions happen at the wall clock time of the observance `DTSTART`, in the offset
+    /// in effect before them. Times skipped by a transition (e.g. 02:30 when clocks spring
+    /// forward at 02:00) resolve to the new offset, repeated times to the old one.
+    pub fn resolve(&self, naive: NaiveDateTime) -> Result<FixedOffset, VTimezoneResolveError> {
+        let latest = self
+            .offsets
+            .iter()
+            .filter_map(|offset| offset.last_onset(naive).map(|onset| (onset, offset)))
+            .max_by_key(|(onset, _)| *onset);
+
+        Ok(match latest {
+            Some((_, offset)) => offset.offset_to()?,
+            // before any transition: what was in effect before the first one
+            None => self
+                .offsets
+                .iter()
+                .min_by_key(|offset| offset.dt_start)
+                .ok_or(VTimezoneResolveError::NoObservances)?
+                .offset_from()?,
+        })
+    }
+}
+
 impl TryFrom<Block> for VTimezone {
     type Error = VTimezoneParseError;
 
@@ -42,7 +95,11 @@ impl TryFrom<Block> for VTimezone {
         let tz_id = block
             .inner_lines
             .iter()
-            .find_map(|l| l.strip_prefix("TZID:"))
+            .find_map(|l| {
+                l.split_once(':')
+                    .filter(|(key, _)| key.eq_ignore_ascii_case("TZID"))
+                    .map(|(_, value)| value)
+            })
             .ok_or(VTimezoneParseError::TZIDTagNotFound)?
             .to_owned();
 
@@ -56,6 +113,106 @@ impl TryFrom<Block> for VTimezone {
     }
 }
 
+impl VTimezoneOffset {
+    /// `TZOFFSETFROM` as a [`FixedOffset`].
+    pub fn offset_from(&self) -> Result<FixedOffset, UtcOffsetParseError> {
+        parse_utc_offset(&self.tz_offset_from)
+    }
+
+    /// `TZOFFSETTO` as a [`FixedOffset`].
+    pub fn offset_to(&self) -> Result<FixedOffset, UtcOffsetParseError> {
+        parse_utc_offset(&self.tz_offset_to)
+    }
+}
+
+impl VTimezoneOffset {
+    /// Most recent onset of this observance at or before the wall clock time `naive`.
+    fn last_onset(&self, naive: NaiveDateTime) -> Option<NaiveDateTime> {
+        if naive < self.dt_start {
+            return None;
+        }
+
+        let rrule = match &self.rrule {
+            Some(rrule) => rrule,
+            None => return Some(self.dt_start),
+        };
+        // UNTIL is in UTC, a few hours of difference do not matter for yearly transitions
+        let until = rrule
+            .common_options()
+            .until
+            .map(|until| until.date().naive_utc());
+
+        (self.dt_start.year()..=naive.year())
+            .rev()
+            .filter_map(|year| self.onset_in(rrule, year))
+            .find(|onset| {
+                self.dt_start <= *onset
+                    && *onset <= naive
+                    && until.is_none_or(|until| *onset <= until)
+            })
+            .or(Some(self.dt_start))
+    }
+
+    /// Onset of this observance in `year` according to `rrule`, `None` if the rule is not a
+    /// yearly one.
+    fn onset_in(&self, rrule: &RRule, year: i32) -> Option<NaiveDateTime> {
+        let time = self.dt_start.time();
+        match rrule {
+            RRule::Yearly(_) => {
+                NaiveDate::from_ymd_opt(year, self.dt_start.month(), self.dt_start.day())
+                    .map(|date| date.and_time(time))
+            }
+            RRule::YearlyByMonthByMonthDay(rrule) => {
+                NaiveDate::from_ymd_opt(year, rrule.month as u32, rrule.month_day as u32)
+                    .map(|date| date.and_time(time))
+            }
+            RRule::YearlyByMonthByDay(rrule) => match &rrule.day {
+                ByDay::Delta(delta) => {
+                    let month_start =
+                        NaiveDate::from_ymd_opt(year, rrule.month as u32, 1)?.and_time(time);
+                    match DateOrDateTime::Floating(month_start).move_by_delta(delta)? {
+                        DateOrDateTime::Floating(onset) => Some(onset),
+                        _ => None,
+                    }
+                }
+                ByDay::Simple(_) => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Parses an RFC 5545 UTC-OFFSET value (3.3.14), `±HHMM` with optional seconds.
+fn parse_utc_offset(value: &str) -> Result<FixedOffset, UtcOffsetParseError> {
+    let invalid_format = || UtcOffsetParseError::InvalidFormat {
+        value: value.to_owned(),
+    };
+
+    let trimmed = value.trim();
+    let (sign, digits) = match trimmed.split_at_checked(1) {
+        Some(("+", digits)) => (1, digits),
+        Some(("-", digits)) => (-1, digits),
+        _ => return Err(invalid_format()),
+    };
+    if !matches!(digits.len(), 4 | 6) || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid_format());
+    }
+
+    let field = |range: std::ops::Range<usize>| digits.get(range).map_or(Ok(0), str::parse::<i32>);
+    let hours = field(0..2).map_err(|_| invalid_format())?;
+    let minutes = field(2..4).map_err(|_| invalid_format())?;
+    let seconds = field(4..6).map_err(|_| invalid_format())?;
+    if minutes > 59 || seconds > 59 {
+        return Err(invalid_format());
+    }
+
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60 + seconds)).ok_or_else(|| {
+        UtcOffsetParseError::OutOfRange {
+            value: value.to_owned(),
+        }
+    })
+}
+
 impl TryFrom<Block> for VTimezoneOffset {
     type Error = VTimezoneOffsetParseError;
 
@@ -75,14 +232,14 @@ impl TryFrom<Block> for VTimezoneOffset {
                 })?;
             let value = tokens.collect::<Vec<_>>().join(":");
 
-            match key {
+            match key.to_ascii_uppercase().as_str() {
                 "TZNAME" => tz_name = Some(value),
                 "TZOFFSETFROM" => tz_offset_from = Some(value),
                 "TZOFFSETTO" => tz_offset_to = Some(value),
                 "DTSTART" => {
-                    dt_start = Some(NaiveDate::parse_from_str(&value, "%Y%m%dT%H%M%S").unwrap())
+                    dt_start = Some(NaiveDateTime::parse_from_str(&value, "%Y%m%dT%H%M%S")?)
                 }
-                "RRULE" => rrule = Some(value.parse().unwrap()),
+                "RRULE" => rrule = Some(value.parse()?),
 
                 _ => {
                     return Err(VTimezoneOffsetParseError::UnsupportedTag {
@@ -118,3 +275,114 @@ impl TryFrom<Block> for VTimezoneOffset {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offset(tz_offset_from: &str, tz_offset_to: &str) -> VTimezoneOffset {
+        VTimezoneOffset {
+            tz_name: "CET".to_owned(),
+            tz_offset_from: tz_offset_from.to_owned(),
+            tz_offset_to: tz_offset_to.to_owned(),
+            dt_start: NaiveDate::from_ymd_opt(1970, 10, 25)
+                .unwrap()
+                .and_hms_opt(3, 0, 0)
+                .unwrap(),
+            rrule: None,
+        }
+    }
+
+    #[test]
+    fn fixed_offsets() {
+        let o = offset("+0200", "+0100");
+        assert_eq!(
+            o.offset_from().unwrap(),
+            FixedOffset::east_opt(7200).unwrap()
+        );
+        assert_eq!(o.offset_to().unwrap(), FixedOffset::east_opt(3600).unwrap());
+
+        let o = offset("-0930", "+013015");
+        assert_eq!(
+            o.offset_from().unwrap(),
+            FixedOffset::west_opt(9 * 3600 + 30 * 60).unwrap()
+        );
+        assert_eq!(
+            o.offset_to().unwrap(),
+            FixedOffset::east_opt(3600 + 30 * 60 + 15).unwrap()
+        );
+
+        for invalid in ["0100", "+1", "+01:00", "+0160", "", "+01000"] {
+            assert!(matches!(
+                offset(invalid, "+0000").offset_from(),
+                Err(UtcOffsetParseError::InvalidFormat { .. })
+            ));
+        }
+        assert!(matches!(
+            offset("+2400", "+0000").offset_from(),
+            Err(UtcOffsetParseError::OutOfRange { .. })
+        ));
+    }
+
+    #[test]
+    fn resolve_spring_forward() {
+        let lines = [
+            "BEGIN:VTIMEZONE",
+            "TZID:Europe/Rome",
+            "BEGIN:DAYLIGHT",
+            "TZOFFSETFROM:+0100",
+            "TZOFFSETTO:+0200",
+            "TZNAME:CEST",
+            "DTSTART:19700329T020000",
+            "RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU",
+            "END:DAYLIGHT",
+            "BEGIN:STANDARD",
+            "TZOFFSETFROM:+0200",
+            "TZOFFSETTO:+0100",
+            "TZNAME:CET",
+            "DTSTART:19701025T030000",
+            "RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU",
+            "END:STANDARD",
+            "END:VTIMEZONE",
+        ]
+        .map(str::to_owned);
+        let block: Block = lines.as_slice().try_into().unwrap();
+        let timezone = VTimezone::try_from(block).unwrap();
+
+        let cet = FixedOffset::east_opt(3600).unwrap();
+        let cest = FixedOffset::east_opt(7200).unwrap();
+        let at = |m, d, h, min| {
+            NaiveDate::from_ymd_opt(2022, m, d)
+                .unwrap()
+                .and_hms_opt(h, min, 0)
+                .unwrap()
+        };
+
+        // clocks go forward on the last Sunday of March 2022, the 27th, at 02:00
+        assert_eq!(timezone.resolve(at(3, 27, 1, 59)).unwrap(), cet);
+        assert_eq!(timezone.resolve(at(3, 27, 2, 0)).unwrap(), cest);
+        assert_eq!(timezone.resolve(at(3, 27, 3, 0)).unwrap(), cest);
+        assert_eq!(timezone.resolve(at(3, 20, 12, 0)).unwrap(), cet);
+        assert_eq!(timezone.resolve(at(7, 1, 12, 0)).unwrap(), cest);
+        // and back on the 30th of October at 03:00
+        assert_eq!(timezone.resolve(at(10, 30, 2, 59)).unwrap(), cest);
+        assert_eq!(timezone.resolve(at(10, 30, 3, 0)).unwrap(), cet);
+        assert_eq!(timezone.resolve(at(1, 1, 0, 0)).unwrap(), cet);
+
+        // before the first transition
+        let before = NaiveDate::from_ymd_opt(1960, 6, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        assert_eq!(timezone.resolve(before).unwrap(), cet);
+
+        assert!(matches!(
+            VTimezone {
+                tz_id: "Empty".to_owned(),
+                offsets: Vec::new()
+            }
+            .resolve(before),
+            Err(VTimezoneResolveError::NoObservances)
+        ));
+    }
+}